@@ -0,0 +1,117 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use gameengine::engine::ecs::component::{Collider, Position, PositionType};
+use gameengine::engine::ecs::spatial::{Aabb, overlaps};
+use gameengine::engine::ecs::world::{EntityId, World};
+use std::hint::black_box;
+
+const COUNTS: [usize; 2] = [1_000, 10_000];
+const COLLIDER_SIZE: usize = 16;
+// Smaller than the collider, so every collider overlaps its neighbours.
+const SPACING: usize = 12;
+
+fn collider_world(count: usize) -> World {
+    let mut world = World::new();
+    let per_row = (count as f64).sqrt().ceil() as usize;
+    for i in 0..count {
+        world.spawn_with((
+            Position {
+                x: ((i % per_row) * SPACING) as f32,
+                y: ((i / per_row) * SPACING) as f32,
+                position_type: PositionType::Abs,
+            },
+            Collider::aabb((0, 0), (COLLIDER_SIZE, COLLIDER_SIZE)),
+        ));
+    }
+
+    world
+}
+
+// Pair finding without a broadphase, every collider against every other one.
+fn brute_force_pairs(bounds: &[(EntityId, Aabb)]) -> usize {
+    let mut pairs = 0;
+    for (idx, (_, a)) in bounds.iter().enumerate() {
+        for (_, b) in &bounds[idx + 1..] {
+            if overlaps(*a, *b) {
+                pairs += 1;
+            }
+        }
+    }
+
+    pairs
+}
+
+fn collisions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("collisions");
+    group.sample_size(10);
+    for count in COUNTS {
+        let mut world = collider_world(count);
+        let entities: Vec<EntityId> = world.query::<EntityId>().collect();
+
+        group.bench_with_input(BenchmarkId::new("get_collision", count), &count, |b, _| {
+            b.iter(|| {
+                for entity_id in &entities {
+                    black_box(world.get_collision(*entity_id).unwrap());
+                }
+            })
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("collect_collisions", count),
+            &count,
+            |b, _| b.iter(|| black_box(world.collect_collisions())),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("move_and_collect", count),
+            &count,
+            |b, _| {
+                b.iter(|| {
+                    for mut position in world.query::<&mut Position>() {
+                        position.x += 1.0;
+                    }
+                    black_box(world.collect_collisions())
+                })
+            },
+        );
+
+        // One move and one query per entity, the way games check a step
+        // before taking the next one.
+        group.bench_with_input(
+            BenchmarkId::new("move_then_query", count),
+            &count,
+            |b, _| {
+                b.iter(|| {
+                    for entity_id in &entities {
+                        world.get_mut::<Position>(entity_id).unwrap().x += 1.0;
+                        black_box(world.get_collision(*entity_id).unwrap());
+                    }
+                })
+            },
+        );
+
+        // Pair finding alone, comparable with `brute_force` below.
+        group.bench_with_input(
+            BenchmarkId::new("broadphase_pairs", count),
+            &count,
+            |b, _| b.iter(|| black_box(world.spatial_grid().pairs().len())),
+        );
+
+        let bounds: Vec<(EntityId, Aabb)> = entities
+            .iter()
+            .map(|entity_id| {
+                let collider = world.fetch::<Collider>(entity_id).unwrap();
+                (
+                    *entity_id,
+                    collider.get_points(world.get_abs_pos(*entity_id).unwrap()),
+                )
+            })
+            .collect();
+        group.bench_with_input(BenchmarkId::new("brute_force", count), &count, |b, _| {
+            b.iter(|| black_box(brute_force_pairs(&bounds)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, collisions);
+criterion_main!(benches);
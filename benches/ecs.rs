@@ -0,0 +1,154 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use gameengine::engine::ecs::component::{Component, Position, PositionType, Size};
+use gameengine::engine::ecs::storage::StorageType;
+use gameengine::engine::ecs::world::{EntityId, World};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::hint::black_box;
+
+const COUNTS: [usize; 2] = [1_000, 10_000];
+
+struct Velocity {
+    x: f32,
+    y: f32,
+}
+
+impl Component for Velocity {}
+
+// The storage layout the world used before archetypes: one hash map per
+// component type, keyed by entity.
+#[derive(Default)]
+struct HashMapWorld {
+    storages: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl HashMapWorld {
+    fn insert<T: 'static>(&mut self, entity_id: EntityId, component: T) {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(HashMap::<EntityId, T>::new()))
+            .downcast_mut::<HashMap<EntityId, T>>()
+            .unwrap()
+            .insert(entity_id, component);
+    }
+
+    fn storage<T: 'static>(&self) -> &HashMap<EntityId, T> {
+        self.storages[&TypeId::of::<T>()].downcast_ref().unwrap()
+    }
+
+    // Takes the storage out so it can be mutated while others are borrowed.
+    fn take<T: 'static>(&mut self) -> Box<dyn Any> {
+        self.storages.remove(&TypeId::of::<T>()).unwrap()
+    }
+
+    fn put_back<T: 'static>(&mut self, storage: Box<dyn Any>) {
+        self.storages.insert(TypeId::of::<T>(), storage);
+    }
+}
+
+fn position(i: usize) -> Position {
+    Position {
+        x: i as f32,
+        y: i as f32,
+        position_type: PositionType::Abs,
+    }
+}
+
+fn archetype_world(count: usize) -> World {
+    populate(World::new(), count)
+}
+
+fn sparse_world(count: usize) -> World {
+    let mut world = World::new();
+    world
+        .register_component::<Size>(StorageType::SparseSet)
+        .unwrap();
+    populate(world, count)
+}
+
+fn populate(mut world: World, count: usize) -> World {
+    for i in 0..count {
+        let entity_id = world.spawn_with((position(i), Velocity { x: 1.0, y: 1.0 }));
+        // Every other entity gets an extra component so iteration spans
+        // several archetypes.
+        if i % 2 == 0 {
+            let _ = world.try_add_component(entity_id, Size { x: 1, y: 1 });
+        }
+    }
+
+    world
+}
+
+fn hashmap_world(count: usize) -> HashMapWorld {
+    let mut entities = World::new();
+    let mut world = HashMapWorld::default();
+    for i in 0..count {
+        let entity_id = entities.spawn();
+        world.insert(entity_id, position(i));
+        world.insert(entity_id, Velocity { x: 1.0, y: 1.0 });
+        if i % 2 == 0 {
+            world.insert(entity_id, Size { x: 1, y: 1 });
+        }
+    }
+
+    world
+}
+
+fn iterate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iterate_position_velocity");
+    for count in COUNTS {
+        let mut world = archetype_world(count);
+        group.bench_with_input(BenchmarkId::new("archetype", count), &count, |b, _| {
+            b.iter(|| {
+                for (mut position, velocity) in world.query::<(&mut Position, &Velocity)>() {
+                    position.x += velocity.x;
+                    position.y += velocity.y;
+                }
+            })
+        });
+
+        let mut world = hashmap_world(count);
+        group.bench_with_input(BenchmarkId::new("hashmap", count), &count, |b, _| {
+            b.iter(|| {
+                let mut storage = world.take::<Position>();
+                let positions = storage
+                    .downcast_mut::<HashMap<EntityId, Position>>()
+                    .unwrap();
+                for (entity_id, velocity) in world.storage::<Velocity>() {
+                    if let Some(position) = positions.get_mut(entity_id) {
+                        position.x += velocity.x;
+                        position.y += velocity.y;
+                    }
+                }
+                world.put_back::<Position>(storage);
+            })
+        });
+    }
+    group.finish();
+}
+
+fn add_remove(c: &mut Criterion) {
+    let mut group = c.benchmark_group("add_remove_component");
+    for count in COUNTS {
+        for (name, mut world) in [
+            ("archetype", archetype_world(count)),
+            ("sparse_set", sparse_world(count)),
+        ] {
+            let entities: Vec<EntityId> = world.query::<EntityId>().collect();
+            group.bench_with_input(BenchmarkId::new(name, count), &count, |b, _| {
+                b.iter(|| {
+                    for entity_id in &entities {
+                        let _ = world.try_add_component(*entity_id, Size { x: 2, y: 2 });
+                    }
+                    for entity_id in &entities {
+                        black_box(world.remove_component::<Size>(entity_id));
+                    }
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, iterate, add_remove);
+criterion_main!(benches);
@@ -1,8 +1,14 @@
+use crate::engine::ecs::schedule::{Schedule, Stage};
+use crate::engine::ecs::collision::detect_collisions;
+use crate::engine::ecs::transform::propagate_transforms;
 use crate::engine::ecs::world::World;
 use crate::engine::game::Game;
 use crate::engine::input::InputState;
+use crate::engine::inspector::Inspector;
 use crate::engine::render::renderer::Renderer;
+use crate::engine::time::Time;
 use crate::{WIN_HEIGHT, WIN_WIDTH};
+use log::{error, warn};
 use softbuffer::{Context, Surface};
 use std::rc::Rc;
 use std::time::Instant;
@@ -22,6 +28,9 @@ pub struct App {
     renderer: Option<Renderer>,
     input_state: InputState,
     pub world: Option<World>,
+    pub schedule: Schedule,
+    pub inspector: Inspector,
+    started: bool,
 }
 
 impl ApplicationHandler for App {
@@ -69,11 +78,47 @@ impl ApplicationHandler for App {
 
                 let world = self.world.as_mut().unwrap();
 
+                if !self.started {
+                    self.schedule
+                        .add_system(Stage::PostUpdate, "propagate_transforms", |world| {
+                            if let Err(err) = propagate_transforms(world) {
+                                warn!("{err}");
+                            }
+                        });
+                    self.schedule
+                        .add_system(Stage::Physics, "detect_collisions", detect_collisions);
+                    if let Some(game) = &mut self.game {
+                        game.setup(&mut self.schedule, world);
+                    }
+                    self.started = true;
+
+                    // Duplicate systems and ordering cycles are setup bugs,
+                    // nothing sensible can run after them.
+                    if let Err(err) = self.schedule.build() {
+                        error!("{err}");
+                        event_loop.exit();
+                        return;
+                    }
+                }
+
+                world.update_events();
+                world.resource_or_insert_with(Time::default).advance(delta_time);
+                world.insert_resource(self.input_state.clone());
+
+                self.inspector.handle_input(&self.input_state);
+
                 // Update
+                run_stage(&mut self.schedule, Stage::Input, world);
+                run_stage(&mut self.schedule, Stage::PreUpdate, world);
                 if let Some(game) = &mut self.game {
                     game.update(delta_time, &self.input_state, world);
                     self.input_state.clear();
                 }
+                world.flush_commands();
+                run_stage(&mut self.schedule, Stage::Update, world);
+                run_stage(&mut self.schedule, Stage::Physics, world);
+                run_stage(&mut self.schedule, Stage::PostUpdate, world);
+                run_stage(&mut self.schedule, Stage::RenderPrep, world);
 
                 if let Some(window) = &self.window {
                     window.request_redraw();
@@ -84,12 +129,15 @@ impl ApplicationHandler for App {
                     if let Some(game) = &mut self.game {
                         game.render(renderer, world);
                     }
+                    self.inspector.render(renderer, world, self.input_state.mouse_pos);
 
                     let mut surface_buffer = surface.buffer_mut().unwrap();
 
                     surface_buffer.copy_from_slice(renderer.buf_as_slice());
                     surface_buffer.present().unwrap();
                 }
+
+                world.clear_trackers();
             }
             WindowEvent::KeyboardInput {
                 device_id: _,
@@ -123,6 +171,14 @@ impl ApplicationHandler for App {
     }
 }
 
+// The schedule is built once after setup, a system added later that breaks it
+// only skips the stage.
+fn run_stage(schedule: &mut Schedule, stage: Stage, world: &mut World) {
+    if let Err(err) = schedule.run_stage(stage, world) {
+        warn!("{err}");
+    }
+}
+
 /*impl Default for App {
     fn default() -> Self {
         Self {
@@ -0,0 +1,370 @@
+use super::component::Component;
+use super::storage::{ComponentTicks, Tick};
+use super::world::EntityId;
+use std::any::{Any, TypeId, type_name};
+use std::collections::HashMap;
+
+pub type ArchetypeId = usize;
+
+pub const EMPTY_ARCHETYPE: ArchetypeId = 0;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct EntityLocation {
+    pub archetype: ArchetypeId,
+    pub row: usize,
+}
+
+pub(crate) trait AnyColumn {
+    fn type_name(&self) -> &'static str;
+    fn ticks(&self, row: usize) -> ComponentTicks;
+    fn swap_remove_drop(&mut self, row: usize);
+    // Swap-removes `row` and pushes the value into `target`, which must be a
+    // column of the same component type.
+    fn move_row(&mut self, row: usize, target: &mut dyn AnyColumn);
+    fn empty(&self) -> Box<dyn AnyColumn>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+pub struct Column<T: Component> {
+    data: Vec<T>,
+    ticks: Vec<ComponentTicks>,
+}
+
+impl<T: Component> Default for Column<T> {
+    fn default() -> Self {
+        Self {
+            data: Vec::new(),
+            ticks: Vec::new(),
+        }
+    }
+}
+
+impl<T: Component> Column<T> {
+    pub fn push(&mut self, component: T, ticks: ComponentTicks) {
+        self.data.push(component);
+        self.ticks.push(ticks);
+    }
+
+    pub fn get(&self, row: usize) -> Option<&T> {
+        self.data.get(row)
+    }
+
+    pub fn get_mut(&mut self, row: usize) -> Option<(&mut T, &mut ComponentTicks)> {
+        Some((self.data.get_mut(row)?, self.ticks.get_mut(row)?))
+    }
+
+    pub fn replace(&mut self, row: usize, component: T, tick: Tick) -> T {
+        self.ticks[row].changed = tick;
+        std::mem::replace(&mut self.data[row], component)
+    }
+
+    pub fn swap_remove(&mut self, row: usize) -> (T, ComponentTicks) {
+        (self.data.swap_remove(row), self.ticks.swap_remove(row))
+    }
+
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn ticks(&self) -> &[ComponentTicks] {
+        &self.ticks
+    }
+
+    pub(crate) fn data_ptr(&mut self) -> *mut T {
+        self.data.as_mut_ptr()
+    }
+
+    pub(crate) fn ticks_ptr(&mut self) -> *mut ComponentTicks {
+        self.ticks.as_mut_ptr()
+    }
+}
+
+impl<T: Component> AnyColumn for Column<T> {
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+
+    fn ticks(&self, row: usize) -> ComponentTicks {
+        self.ticks[row]
+    }
+
+    fn swap_remove_drop(&mut self, row: usize) {
+        self.swap_remove(row);
+    }
+
+    fn move_row(&mut self, row: usize, target: &mut dyn AnyColumn) {
+        let (component, ticks) = self.swap_remove(row);
+        target
+            .as_any_mut()
+            .downcast_mut::<Column<T>>()
+            .expect("column moved into a column of another type")
+            .push(component, ticks);
+    }
+
+    fn empty(&self) -> Box<dyn AnyColumn> {
+        Box::new(Column::<T>::default())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// Entities sharing the exact same set of component types live in one
+// archetype, each component type is a contiguous column indexed by row.
+pub struct Archetype {
+    id: ArchetypeId,
+    types: Vec<TypeId>,
+    columns: Vec<Box<dyn AnyColumn>>,
+    entities: Vec<EntityId>,
+    add_edges: HashMap<TypeId, ArchetypeId>,
+    remove_edges: HashMap<TypeId, ArchetypeId>,
+}
+
+impl Archetype {
+    fn new(id: ArchetypeId, types: Vec<TypeId>, columns: Vec<Box<dyn AnyColumn>>) -> Self {
+        Self {
+            id,
+            types,
+            columns,
+            entities: Vec::new(),
+            add_edges: HashMap::new(),
+            remove_edges: HashMap::new(),
+        }
+    }
+
+    pub fn id(&self) -> ArchetypeId {
+        self.id
+    }
+
+    pub fn types(&self) -> &[TypeId] {
+        &self.types
+    }
+
+    pub fn entities(&self) -> &[EntityId] {
+        &self.entities
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn contains(&self, type_id: TypeId) -> bool {
+        self.types.binary_search(&type_id).is_ok()
+    }
+
+    pub fn column<T: Component>(&self) -> Option<&Column<T>> {
+        let idx = self.types.binary_search(&TypeId::of::<T>()).ok()?;
+        self.columns[idx].as_any().downcast_ref::<Column<T>>()
+    }
+
+    pub fn column_mut<T: Component>(&mut self) -> Option<&mut Column<T>> {
+        let idx = self.types.binary_search(&TypeId::of::<T>()).ok()?;
+        self.columns[idx].as_any_mut().downcast_mut::<Column<T>>()
+    }
+
+    pub(crate) fn column_type_name(&self, type_id: TypeId) -> Option<&'static str> {
+        let idx = self.types.binary_search(&type_id).ok()?;
+        Some(self.columns[idx].type_name())
+    }
+
+    pub(crate) fn column_ticks(&self, type_id: TypeId, row: usize) -> Option<ComponentTicks> {
+        let idx = self.types.binary_search(&type_id).ok()?;
+        Some(self.columns[idx].ticks(row))
+    }
+
+    fn push_entity(&mut self, entity_id: EntityId) -> usize {
+        self.entities.push(entity_id);
+        self.entities.len() - 1
+    }
+
+    // Returns the entity that was swapped into `row`, if any.
+    fn swap_remove_entity(&mut self, row: usize) -> Option<EntityId> {
+        self.entities.swap_remove(row);
+        self.entities.get(row).copied()
+    }
+}
+
+pub struct Archetypes {
+    archetypes: Vec<Archetype>,
+    index: HashMap<Vec<TypeId>, ArchetypeId>,
+}
+
+impl Default for Archetypes {
+    fn default() -> Self {
+        let mut archetypes = Self {
+            archetypes: Vec::new(),
+            index: HashMap::new(),
+        };
+        archetypes.create(Vec::new(), Vec::new());
+
+        archetypes
+    }
+}
+
+pub(crate) struct MovedEntity {
+    pub row: usize,
+    pub swapped: Option<EntityId>,
+}
+
+impl Archetypes {
+    pub fn get(&self, id: ArchetypeId) -> Option<&Archetype> {
+        self.archetypes.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: ArchetypeId) -> Option<&mut Archetype> {
+        self.archetypes.get_mut(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Archetype> {
+        self.archetypes.iter()
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut Archetype> {
+        self.archetypes.iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.archetypes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.archetypes.is_empty()
+    }
+
+    pub(crate) fn push_entity(&mut self, archetype: ArchetypeId, entity_id: EntityId) -> usize {
+        self.archetypes[archetype].push_entity(entity_id)
+    }
+
+    pub(crate) fn with_component<T: Component>(&mut self, source: ArchetypeId) -> ArchetypeId {
+        let type_id = TypeId::of::<T>();
+        if let Some(&target) = self.archetypes[source].add_edges.get(&type_id) {
+            return target;
+        }
+
+        let archetype = &self.archetypes[source];
+        let mut types = archetype.types.clone();
+        let idx = types.binary_search(&type_id).unwrap_err();
+        types.insert(idx, type_id);
+
+        let target = match self.index.get(&types) {
+            Some(&target) => target,
+            None => {
+                let mut columns: Vec<Box<dyn AnyColumn>> =
+                    archetype.columns.iter().map(|column| column.empty()).collect();
+                columns.insert(idx, Box::new(Column::<T>::default()));
+                self.create(types, columns)
+            }
+        };
+        self.link(source, target, type_id);
+
+        target
+    }
+
+    pub(crate) fn without_component(&mut self, source: ArchetypeId, type_id: TypeId) -> ArchetypeId {
+        if let Some(&target) = self.archetypes[source].remove_edges.get(&type_id) {
+            return target;
+        }
+
+        let archetype = &self.archetypes[source];
+        let Ok(idx) = archetype.types.binary_search(&type_id) else {
+            return source;
+        };
+        let mut types = archetype.types.clone();
+        types.remove(idx);
+
+        let target = match self.index.get(&types) {
+            Some(&target) => target,
+            None => {
+                let columns = archetype
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .filter(|(column_idx, _)| *column_idx != idx)
+                    .map(|(_, column)| column.empty())
+                    .collect();
+                self.create(types, columns)
+            }
+        };
+        self.link(target, source, type_id);
+
+        target
+    }
+
+    // Moves the entity at `location` into `target`. Columns missing from the
+    // target are handed to `take`, which must swap-remove `row` from them.
+    pub(crate) fn move_entity<F>(
+        &mut self,
+        location: EntityLocation,
+        target: ArchetypeId,
+        mut take: F,
+    ) -> MovedEntity
+    where
+        F: FnMut(TypeId, &mut dyn AnyColumn, usize),
+    {
+        let (source, destination) = self.pair_mut(location.archetype, target);
+        for (idx, column) in source.columns.iter_mut().enumerate() {
+            let type_id = source.types[idx];
+            match destination.types.binary_search(&type_id) {
+                Ok(target_idx) => {
+                    column.move_row(location.row, destination.columns[target_idx].as_mut())
+                }
+                Err(_) => take(type_id, column.as_mut(), location.row),
+            }
+        }
+
+        let entity_id = source.entities[location.row];
+        let swapped = source.swap_remove_entity(location.row);
+        let row = destination.push_entity(entity_id);
+
+        MovedEntity { row, swapped }
+    }
+
+    // Drops every component at `location`, returning the component types that
+    // were removed and the entity swapped into the freed row.
+    pub(crate) fn remove_entity(
+        &mut self,
+        location: EntityLocation,
+    ) -> (Vec<TypeId>, Option<EntityId>) {
+        let archetype = &mut self.archetypes[location.archetype];
+        for column in archetype.columns.iter_mut() {
+            column.swap_remove_drop(location.row);
+        }
+        let swapped = archetype.swap_remove_entity(location.row);
+
+        (archetype.types.clone(), swapped)
+    }
+
+    fn create(&mut self, types: Vec<TypeId>, columns: Vec<Box<dyn AnyColumn>>) -> ArchetypeId {
+        let id = self.archetypes.len();
+        self.index.insert(types.clone(), id);
+        self.archetypes.push(Archetype::new(id, types, columns));
+
+        id
+    }
+
+    fn link(&mut self, smaller: ArchetypeId, larger: ArchetypeId, type_id: TypeId) {
+        self.archetypes[smaller].add_edges.insert(type_id, larger);
+        self.archetypes[larger].remove_edges.insert(type_id, smaller);
+    }
+
+    fn pair_mut(&mut self, a: ArchetypeId, b: ArchetypeId) -> (&mut Archetype, &mut Archetype) {
+        assert_ne!(a, b, "cannot move an entity into its own archetype");
+        if a < b {
+            let (left, right) = self.archetypes.split_at_mut(b);
+            (&mut left[a], &mut right[0])
+        } else {
+            let (left, right) = self.archetypes.split_at_mut(a);
+            (&mut right[0], &mut left[b])
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use super::bundle::Bundle;
+use super::world::{EntityId, World};
+
+pub struct EntityBuilder<'w> {
+    world: &'w mut World,
+    entity_id: EntityId,
+}
+
+impl<'w> EntityBuilder<'w> {
+    pub fn new(world: &'w mut World) -> Self {
+        let entity_id = world.spawn();
+        Self { world, entity_id }
+    }
+
+    pub fn with<B: Bundle>(&mut self, bundle: B) -> &mut Self {
+        bundle.insert_into(self.world, self.entity_id);
+        self
+    }
+
+    pub fn child_of(&mut self, parent_id: EntityId) -> &mut Self {
+        self.world.set_parent(self.entity_id, parent_id);
+        self
+    }
+
+    pub fn with_child<F>(&mut self, build: F) -> &mut Self
+    where
+        F: FnOnce(&mut EntityBuilder),
+    {
+        let parent_id = self.entity_id;
+        let mut child = EntityBuilder::new(self.world);
+        child.child_of(parent_id);
+        build(&mut child);
+        self
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        self.world
+    }
+
+    pub fn id(&self) -> EntityId {
+        self.entity_id
+    }
+}
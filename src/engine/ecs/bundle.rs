@@ -0,0 +1,33 @@
+use super::component::Component;
+use super::world::{ComponentStorage, EntityId, World};
+
+pub trait Bundle {
+    fn insert_into(self, world: &mut World, entity_id: EntityId);
+}
+
+impl<T: Component> Bundle for T {
+    fn insert_into(self, world: &mut World, entity_id: EntityId) {
+        world.add_component(entity_id, self);
+    }
+}
+
+macro_rules! impl_bundle_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: Bundle),*> Bundle for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn insert_into(self, world: &mut World, entity_id: EntityId) {
+                let ($($name,)*) = self;
+                $($name.insert_into(world, entity_id);)*
+            }
+        }
+    };
+}
+
+impl_bundle_tuple!(A);
+impl_bundle_tuple!(A, B);
+impl_bundle_tuple!(A, B, C);
+impl_bundle_tuple!(A, B, C, D);
+impl_bundle_tuple!(A, B, C, D, E);
+impl_bundle_tuple!(A, B, C, D, E, F);
+impl_bundle_tuple!(A, B, C, D, E, F, G);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H);
@@ -0,0 +1,619 @@
+use super::component::CollisionSide;
+use super::event::EventWriter;
+use super::world::{EntityId, World};
+use glam::Vec2;
+use std::collections::{HashMap, HashSet};
+
+// Tolerance in pixels when picking the touching features of two shapes.
+const FEATURE_EPSILON: f32 = 1e-3;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Manifold {
+    // Unit vector pointing from the first shape towards the second.
+    pub normal: Vec2,
+    // Overlap along `normal`, zero when the shapes only touch.
+    pub depth: f32,
+    // One or two points in world space, halfway between both surfaces.
+    pub points: Vec<Vec2>,
+    // The first shape lies fully inside the second, without touching its
+    // surface.
+    pub first_inside: bool,
+    // The second shape lies fully inside the first.
+    pub second_inside: bool,
+    // Outward normals of the second shape's edges that the first one touches
+    // from the inside, empty unless it lies within the second.
+    pub first_touches: Vec<Vec2>,
+    // The same for the second shape lying within the first.
+    pub second_touches: Vec<Vec2>,
+}
+
+impl Manifold {
+    // Shortest move that separates the first shape from the second.
+    pub fn mtv(&self) -> Vec2 {
+        -self.normal * self.depth
+    }
+
+    // The same manifold seen from the second shape.
+    pub fn flipped(&self) -> Self {
+        Self {
+            normal: -self.normal,
+            depth: self.depth,
+            points: self.points.clone(),
+            first_inside: self.second_inside,
+            second_inside: self.first_inside,
+            first_touches: self.second_touches.clone(),
+            second_touches: self.first_touches.clone(),
+        }
+    }
+
+    // Side of the first shape facing the second, along the dominant axis of
+    // the normal. A shape inside the other is contained, or faces the edge it
+    // touches from the inside. A corner that only grazes the other shape has
+    // no side.
+    pub fn side(&self) -> Option<CollisionSide> {
+        if self.first_inside || self.second_inside {
+            return Some(CollisionSide::Contained);
+        }
+        if let Some(normal) = self.first_touches.first() {
+            return Some(side_along(*normal, 0));
+        }
+        if self.depth <= FEATURE_EPSILON && self.points.len() < 2 {
+            return None;
+        }
+
+        Some(side_along(self.normal, self.depth.round() as usize))
+    }
+
+    // All sides of the first shape facing the second, as the old side sets
+    // reported them. A box resting in a corner of its container touches two.
+    pub fn sides(&self) -> HashSet<CollisionSide> {
+        if !self.first_touches.is_empty() {
+            return self
+                .first_touches
+                .iter()
+                .map(|normal| side_along(*normal, 0))
+                .collect();
+        }
+
+        self.side().into_iter().collect()
+    }
+}
+
+fn side_along(normal: Vec2, depth: usize) -> CollisionSide {
+    if normal.x.abs() > normal.y.abs() {
+        if normal.x > 0.0 {
+            CollisionSide::Right(depth)
+        } else {
+            CollisionSide::Left(depth)
+        }
+    } else if normal.y > 0.0 {
+        CollisionSide::Bottom(depth)
+    } else {
+        CollisionSide::Top(depth)
+    }
+}
+
+// Convex core grown by `radius`. Polygons have no radius, a circle is a
+// single point and a capsule a segment. Vertices are in world space and
+// ordered around the hull.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RoundedConvex {
+    pub vertices: Vec<Vec2>,
+    pub radius: f32,
+}
+
+impl RoundedConvex {
+    pub fn polygon(vertices: Vec<Vec2>) -> Self {
+        Self {
+            vertices,
+            radius: 0.0,
+        }
+    }
+
+    pub fn circle(center: Vec2, radius: f32) -> Self {
+        Self {
+            vertices: vec![center],
+            radius,
+        }
+    }
+
+    pub fn capsule(start: Vec2, end: Vec2, radius: f32) -> Self {
+        Self {
+            vertices: vec![start, end],
+            radius,
+        }
+    }
+
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let (min, max) = self.vertices.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), vertex| (min.min(*vertex), max.max(*vertex)),
+        );
+
+        (min - self.radius, max + self.radius)
+    }
+
+    // Whether `other` lies inside this shape by more than the feature
+    // tolerance. Both are convex, so checking the rounded vertices suffices.
+    pub fn contains(&self, other: &RoundedConvex) -> bool {
+        let (min, max) = self.bounds();
+        let (other_min, other_max) = other.bounds();
+        if !(other_min.cmpgt(min).all() && other_max.cmplt(max).all()) {
+            return false;
+        }
+
+        !other.vertices.is_empty()
+            && other
+                .vertices
+                .iter()
+                .all(|vertex| self.signed_distance(*vertex) + other.radius < -FEATURE_EPSILON)
+    }
+
+    // Outward normals of the polygon edges `other` touches from the inside,
+    // empty unless it lies within this shape. Round shapes have no edges to
+    // report.
+    pub fn touched_from_inside(&self, other: &RoundedConvex) -> Vec<Vec2> {
+        let (min, max) = self.bounds();
+        let (other_min, other_max) = other.bounds();
+        if self.vertices.len() < 3
+            || self.radius > 0.0
+            || other.vertices.is_empty()
+            || !(other_min.cmpge(min - FEATURE_EPSILON).all()
+                && other_max.cmple(max + FEATURE_EPSILON).all())
+            || !other
+                .vertices
+                .iter()
+                .all(|vertex| self.signed_distance(*vertex) + other.radius <= FEATURE_EPSILON)
+        {
+            return Vec::new();
+        }
+
+        let center = self.vertices.iter().sum::<Vec2>() / self.vertices.len() as f32;
+        self.edges()
+            .filter_map(|(start, end)| {
+                let normal = (end - start).perp().try_normalize()?;
+                let normal = if (start - center).dot(normal) < 0.0 {
+                    -normal
+                } else {
+                    normal
+                };
+                let reach = other.project(normal).1 + other.radius;
+                (reach >= start.dot(normal) - FEATURE_EPSILON).then_some(normal)
+            })
+            .collect()
+    }
+
+    // Distance from `point` to the surface, negative inside. Inside a polygon
+    // core the nearest edge line is the closest of all support lines.
+    fn signed_distance(&self, point: Vec2) -> f32 {
+        let inside = if self.vertices.len() >= 3 {
+            self.axes()
+                .into_iter()
+                .map(|axis| {
+                    let (min, max) = self.project(axis);
+                    let position = point.dot(axis);
+                    (position - min).min(max - position)
+                })
+                .fold(f32::INFINITY, f32::min)
+        } else {
+            0.0
+        };
+        let core = if inside > 0.0 {
+            -inside
+        } else {
+            self.edges()
+                .map(|(start, end)| closest_on_segment(point, start, end).distance(point))
+                .fold(f32::INFINITY, f32::min)
+        };
+
+        core - self.radius
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let count = self.vertices.len();
+        // A segment has a single edge, a point is a degenerate one.
+        let edges = match count {
+            0 => 0,
+            1 | 2 => 1,
+            _ => count,
+        };
+        (0..edges).map(move |idx| (self.vertices[idx], self.vertices[(idx + 1) % count]))
+    }
+
+    // Separating axis candidates: edge normals, plus the direction of a
+    // segment so collinear segments are not mistaken for overlapping.
+    fn axes(&self) -> Vec<Vec2> {
+        let mut axes: Vec<Vec2> = self
+            .edges()
+            .filter_map(|(start, end)| (end - start).perp().try_normalize())
+            .collect();
+        if self.vertices.len() == 2
+            && let Some(direction) = (self.vertices[1] - self.vertices[0]).try_normalize()
+        {
+            axes.push(direction);
+        }
+
+        axes
+    }
+
+    // Vertices furthest along `direction`, as a segment ordered along
+    // `tangent`. Both ends are the same vertex unless an edge faces `direction`.
+    fn feature(&self, direction: Vec2, tangent: Vec2) -> (Vec2, Vec2) {
+        let support = self.project(direction).1;
+        let mut extreme = self
+            .vertices
+            .iter()
+            .filter(|vertex| vertex.dot(direction) >= support - FEATURE_EPSILON);
+        let first = *extreme.next().unwrap_or(&self.vertices[0]);
+
+        extreme.fold((first, first), |(start, end), vertex| {
+            if vertex.dot(tangent) < start.dot(tangent) {
+                (*vertex, end)
+            } else if vertex.dot(tangent) > end.dot(tangent) {
+                (start, *vertex)
+            } else {
+                (start, end)
+            }
+        })
+    }
+
+    fn project(&self, axis: Vec2) -> (f32, f32) {
+        self.vertices
+            .iter()
+            .map(|vertex| vertex.dot(axis))
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+                (min.min(value), max.max(value))
+            })
+    }
+}
+
+// Narrowphase between two rounded convex shapes. Overlapping cores are
+// resolved with SAT, separated cores by their closest features, which also
+// covers circles and capsules. Touching shapes report a zero depth.
+pub fn intersect(a: &RoundedConvex, b: &RoundedConvex) -> Option<Manifold> {
+    if a.vertices.is_empty() || b.vertices.is_empty() {
+        return None;
+    }
+    let radius = a.radius + b.radius;
+
+    let (normal, depth) = match sat(a, b) {
+        Some((normal, depth)) => (normal, depth + radius),
+        None => {
+            let (closest_a, closest_b) = closest_points(a, b);
+            let offset = closest_b - closest_a;
+            let distance = offset.length();
+            if distance > radius {
+                return None;
+            }
+            (offset.try_normalize().unwrap_or(Vec2::Y), radius - distance)
+        }
+    };
+
+    Some(Manifold {
+        normal,
+        depth,
+        points: contact_points(a, b, normal),
+        first_inside: b.contains(a),
+        second_inside: a.contains(b),
+        first_touches: b.touched_from_inside(a),
+        second_touches: a.touched_from_inside(b),
+    })
+}
+
+// Clips the features of both cores facing each other against one another
+// along the tangent, two parallel edges give two points, anything else one.
+fn contact_points(a: &RoundedConvex, b: &RoundedConvex, normal: Vec2) -> Vec<Vec2> {
+    let tangent = normal.perp();
+    let (a_start, a_end) = a.feature(normal, tangent);
+    let (b_start, b_end) = b.feature(-normal, tangent);
+    let (a_min, a_max) = (a_start.dot(tangent), a_end.dot(tangent));
+    let (b_min, b_max) = (b_start.dot(tangent), b_end.dot(tangent));
+
+    let low = a_min.max(b_min);
+    let high = a_max.min(b_max);
+    let positions = if high - low > FEATURE_EPSILON {
+        vec![low, high]
+    } else {
+        vec![(low + high) / 2.0]
+    };
+
+    positions
+        .into_iter()
+        .map(|position| {
+            let on_a = lerp_feature(a_start, a_end, a_min, a_max, position) + normal * a.radius;
+            let on_b = lerp_feature(b_start, b_end, b_min, b_max, position) - normal * b.radius;
+            (on_a + on_b) / 2.0
+        })
+        .collect()
+}
+
+// Point of the feature at `position` along the tangent, clamped to its ends.
+fn lerp_feature(start: Vec2, end: Vec2, min: f32, max: f32, position: f32) -> Vec2 {
+    if max - min <= FEATURE_EPSILON {
+        return start;
+    }
+
+    start.lerp(end, ((position - min) / (max - min)).clamp(0.0, 1.0))
+}
+
+// Smallest overlap over all candidate axes, `None` once one separates the
+// cores. Two points never share an axis and only overlap when equal.
+fn sat(a: &RoundedConvex, b: &RoundedConvex) -> Option<(Vec2, f32)> {
+    let axes: Vec<Vec2> = a.axes().into_iter().chain(b.axes()).collect();
+    if axes.is_empty() {
+        return (a.vertices[0] == b.vertices[0]).then_some((Vec2::Y, 0.0));
+    }
+
+    let mut best: Option<(Vec2, f32)> = None;
+    for axis in axes {
+        let (min_a, max_a) = a.project(axis);
+        let (min_b, max_b) = b.project(axis);
+        let forward = max_a - min_b;
+        let backward = max_b - min_a;
+        if forward < 0.0 || backward < 0.0 {
+            return None;
+        }
+
+        let (normal, depth) = if forward <= backward {
+            (axis, forward)
+        } else {
+            (-axis, backward)
+        };
+        if best.is_none_or(|(_, best_depth)| depth < best_depth) {
+            best = Some((normal, depth));
+        }
+    }
+
+    best
+}
+
+// Closest pair of points between two disjoint convex cores, always found
+// between a vertex of one and an edge of the other.
+fn closest_points(a: &RoundedConvex, b: &RoundedConvex) -> (Vec2, Vec2) {
+    let mut best = (a.vertices[0], b.vertices[0]);
+    let mut best_distance = f32::INFINITY;
+
+    for vertex in &a.vertices {
+        for (start, end) in b.edges() {
+            let point = closest_on_segment(*vertex, start, end);
+            let distance = point.distance_squared(*vertex);
+            if distance < best_distance {
+                best = (*vertex, point);
+                best_distance = distance;
+            }
+        }
+    }
+    for vertex in &b.vertices {
+        for (start, end) in a.edges() {
+            let point = closest_on_segment(*vertex, start, end);
+            let distance = point.distance_squared(*vertex);
+            if distance < best_distance {
+                best = (point, *vertex);
+                best_distance = distance;
+            }
+        }
+    }
+
+    best
+}
+
+fn closest_on_segment(point: Vec2, start: Vec2, end: Vec2) -> Vec2 {
+    let segment = end - start;
+    let length = segment.length_squared();
+    if length == 0.0 {
+        return start;
+    }
+    let t = ((point - start).dot(segment) / length).clamp(0.0, 1.0);
+
+    start + segment * t
+}
+
+// Sent by `detect_collisions`. Pairs are ordered by id and the manifold normal
+// points from the first entity towards the second.
+#[derive(Clone, Debug)]
+pub struct CollisionStarted {
+    pub entities: (EntityId, EntityId),
+    pub manifold: Manifold,
+    pub sensor: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct CollisionOngoing {
+    pub entities: (EntityId, EntityId),
+    pub manifold: Manifold,
+    pub sensor: bool,
+}
+
+// Also sent when either entity was despawned or stopped colliding because of
+// its layers or ignore list.
+#[derive(Clone, Debug)]
+pub struct CollisionEnded {
+    pub entities: (EntityId, EntityId),
+    pub sensor: bool,
+}
+
+// Pairs seen by the previous pass, with their sensor flag.
+#[derive(Default)]
+struct ContactPairs(HashMap<(EntityId, EntityId), bool>);
+
+// Compares this frame's overlapping pairs with the previous pass and sends
+// the matching started, ongoing and ended events. A pair counts when either
+// side reports it, so a parent and its child collider still pair up.
+pub fn detect_collisions(world: &mut World) {
+    let mut current: HashMap<(EntityId, EntityId), (Manifold, bool)> = HashMap::new();
+    for (entity_id, info) in world.collect_collisions() {
+        for (other_id, collision) in info.collision {
+            if entity_id < other_id {
+                current.insert(
+                    (entity_id, other_id),
+                    (collision.manifold, collision.sensor),
+                );
+            } else {
+                current
+                    .entry((other_id, entity_id))
+                    .or_insert_with(|| (collision.manifold.flipped(), collision.sensor));
+            }
+        }
+    }
+    let previous = world.remove_resource::<ContactPairs>().unwrap_or_default();
+
+    let mut started = Vec::new();
+    let mut ongoing = Vec::new();
+    for (entities, (manifold, sensor)) in &current {
+        let (manifold, sensor) = (manifold.clone(), *sensor);
+        if previous.0.contains_key(entities) {
+            ongoing.push(CollisionOngoing {
+                entities: *entities,
+                manifold,
+                sensor,
+            });
+        } else {
+            started.push(CollisionStarted {
+                entities: *entities,
+                manifold,
+                sensor,
+            });
+        }
+    }
+    let mut ended: Vec<CollisionEnded> = previous
+        .0
+        .into_iter()
+        .filter(|(entities, _)| !current.contains_key(entities))
+        .map(|(entities, sensor)| CollisionEnded { entities, sensor })
+        .collect();
+
+    // Stable order for readers, hash maps iterate at random.
+    started.sort_by_key(|event| event.entities);
+    ongoing.sort_by_key(|event| event.entities);
+    ended.sort_by_key(|event| event.entities);
+
+    world.insert_resource(ContactPairs(
+        current
+            .into_iter()
+            .map(|(entities, (_, sensor))| (entities, sensor))
+            .collect(),
+    ));
+    EventWriter::new(world).send_batch(started);
+    EventWriter::new(world).send_batch(ongoing);
+    EventWriter::new(world).send_batch(ended);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f32, y: f32, size: f32) -> RoundedConvex {
+        RoundedConvex::polygon(vec![
+            Vec2::new(x, y),
+            Vec2::new(x + size, y),
+            Vec2::new(x + size, y + size),
+            Vec2::new(x, y + size),
+        ])
+    }
+
+    fn assert_close(found: Vec2, expected: Vec2) {
+        assert!(found.abs_diff_eq(expected, 1e-4), "{found} != {expected}");
+    }
+
+    #[test]
+    fn overlapping_boxes_share_an_edge() {
+        let manifold = intersect(&square(0.0, 0.0, 10.0), &square(8.0, 2.0, 10.0)).unwrap();
+
+        assert_close(manifold.normal, Vec2::X);
+        assert!((manifold.depth - 2.0).abs() < 1e-4);
+        assert_eq!(manifold.points.len(), 2);
+        for point in &manifold.points {
+            assert!((point.x - 9.0).abs() < 1e-4 && (2.0..=10.0).contains(&point.y));
+        }
+        assert_eq!(manifold.side(), Some(CollisionSide::Right(2)));
+        assert_close(manifold.mtv(), Vec2::new(-2.0, 0.0));
+    }
+
+    #[test]
+    fn touching_boxes_have_zero_depth_and_apart_ones_no_manifold() {
+        let touching = intersect(&square(0.0, 0.0, 10.0), &square(0.0, 10.0, 10.0)).unwrap();
+        assert_close(touching.normal, Vec2::Y);
+        assert!(touching.depth.abs() < 1e-4);
+        assert_eq!(touching.side(), Some(CollisionSide::Bottom(0)));
+
+        assert!(intersect(&square(0.0, 0.0, 10.0), &square(11.0, 0.0, 10.0)).is_none());
+    }
+
+    #[test]
+    fn circles_push_apart_along_their_centers() {
+        let a = RoundedConvex::circle(Vec2::ZERO, 3.0);
+        let b = RoundedConvex::circle(Vec2::new(3.0, 4.0), 3.0);
+        let manifold = intersect(&a, &b).unwrap();
+
+        assert_close(manifold.normal, Vec2::new(0.6, 0.8));
+        assert!((manifold.depth - 1.0).abs() < 1e-4);
+        assert_eq!(manifold.points.len(), 1);
+        assert!(intersect(&a, &RoundedConvex::circle(Vec2::new(6.0, 8.0), 3.0)).is_none());
+    }
+
+    #[test]
+    fn circle_against_box_face_and_corner() {
+        let square = square(0.0, 0.0, 10.0);
+        let face = intersect(&square, &RoundedConvex::circle(Vec2::new(14.0, 5.0), 5.0)).unwrap();
+        assert_close(face.normal, Vec2::X);
+        assert!((face.depth - 1.0).abs() < 1e-4);
+
+        let corner = RoundedConvex::circle(Vec2::new(13.0, 14.0), 6.0);
+        let manifold = intersect(&square, &corner).unwrap();
+        assert_close(manifold.normal, Vec2::new(0.6, 0.8));
+        assert!((manifold.depth - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn capsule_against_box_and_circle() {
+        let capsule = RoundedConvex::capsule(Vec2::ZERO, Vec2::new(0.0, 20.0), 2.0);
+        let manifold = intersect(&capsule, &square(1.0, 5.0, 10.0)).unwrap();
+        assert_close(manifold.normal, Vec2::X);
+        assert!((manifold.depth - 1.0).abs() < 1e-4);
+        assert_eq!(manifold.points.len(), 2);
+
+        let below = intersect(&capsule, &RoundedConvex::circle(Vec2::new(0.0, 24.0), 3.0)).unwrap();
+        assert_close(below.normal, Vec2::Y);
+        assert!((below.depth - 1.0).abs() < 1e-4);
+        assert!(intersect(&capsule, &RoundedConvex::circle(Vec2::new(6.0, 10.0), 3.0)).is_none());
+    }
+
+    #[test]
+    fn swapped_shapes_give_the_flipped_manifold() {
+        let shapes = [
+            square(0.0, 0.0, 10.0),
+            RoundedConvex::circle(Vec2::new(11.0, 6.0), 4.0),
+            RoundedConvex::capsule(Vec2::new(5.0, 8.0), Vec2::new(5.0, 16.0), 3.0),
+        ];
+        for a in &shapes {
+            for b in &shapes {
+                if std::ptr::eq(a, b) {
+                    continue;
+                }
+                let forward = intersect(a, b).unwrap();
+                let backward = intersect(b, a).unwrap();
+                assert_close(backward.normal, -forward.normal);
+                assert!((backward.depth - forward.depth).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn shape_inside_a_box_is_contained() {
+        let container = square(0.0, 0.0, 100.0);
+        let manifold = intersect(
+            &RoundedConvex::circle(Vec2::new(50.0, 50.0), 5.0),
+            &container,
+        )
+        .unwrap();
+        assert!(manifold.first_inside && !manifold.second_inside);
+        assert_eq!(manifold.side(), Some(CollisionSide::Contained));
+        assert!(manifold.flipped().second_inside);
+
+        let resting = intersect(&square(90.0, 90.0, 10.0), &container).unwrap();
+        assert_eq!(
+            resting.sides(),
+            HashSet::from([CollisionSide::Right(0), CollisionSide::Bottom(0)])
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use super::bundle::Bundle;
+use super::component::Component;
+use super::world::{EntityId, World};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct PendingEntity(usize);
+
+// Commands may reference entities spawned earlier in the same buffer, those
+// only get a real id once the buffer is applied.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum CommandTarget {
+    Entity(EntityId),
+    Pending(PendingEntity),
+}
+
+impl From<EntityId> for CommandTarget {
+    fn from(entity_id: EntityId) -> Self {
+        CommandTarget::Entity(entity_id)
+    }
+}
+
+impl From<PendingEntity> for CommandTarget {
+    fn from(pending: PendingEntity) -> Self {
+        CommandTarget::Pending(pending)
+    }
+}
+
+type Command = Box<dyn FnOnce(&mut World, &mut Vec<EntityId>)>;
+
+#[derive(Default)]
+pub struct Commands {
+    queue: Vec<Command>,
+    spawned: usize,
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn push<F>(&mut self, command: F) -> &mut Self
+    where
+        F: FnOnce(&mut World) + 'static,
+    {
+        self.queue.push(Box::new(move |world, _| command(world)));
+        self
+    }
+
+    pub fn spawn<B: Bundle + 'static>(&mut self, bundle: B) -> PendingEntity {
+        let pending = PendingEntity(self.spawned);
+        self.spawned += 1;
+        self.queue.push(Box::new(move |world, spawned| {
+            spawned.push(world.spawn_with(bundle));
+        }));
+
+        pending
+    }
+
+    pub fn despawn(&mut self, target: impl Into<CommandTarget>) -> &mut Self {
+        self.push_for(target.into(), |world, entity_id| world.remove_entity(entity_id))
+    }
+
+    pub fn despawn_recursive(&mut self, target: impl Into<CommandTarget>) -> &mut Self {
+        self.push_for(target.into(), |world, entity_id| {
+            world.despawn_recursive(entity_id)
+        })
+    }
+
+    pub fn add<B: Bundle + 'static>(
+        &mut self,
+        target: impl Into<CommandTarget>,
+        bundle: B,
+    ) -> &mut Self {
+        self.push_for(target.into(), move |world, entity_id| {
+            world.add_bundle(entity_id, bundle);
+        })
+    }
+
+    pub fn remove<T: Component>(&mut self, target: impl Into<CommandTarget>) -> &mut Self {
+        self.push_for(target.into(), |world, entity_id| {
+            world.remove_component::<T>(&entity_id);
+        })
+    }
+
+    pub fn set_parent(
+        &mut self,
+        target: impl Into<CommandTarget>,
+        parent: impl Into<CommandTarget>,
+    ) -> &mut Self {
+        let (target, parent) = (target.into(), parent.into());
+        self.queue.push(Box::new(move |world, spawned| {
+            if let (Some(entity_id), Some(parent_id)) =
+                (resolve(target, spawned), resolve(parent, spawned))
+            {
+                world.set_parent(entity_id, parent_id);
+            }
+        }));
+        self
+    }
+
+    pub fn remove_parent(&mut self, target: impl Into<CommandTarget>) -> &mut Self {
+        self.push_for(target.into(), |world, entity_id| {
+            world.remove_parent(entity_id);
+        })
+    }
+
+    pub fn append(&mut self, other: &mut Commands) -> &mut Self {
+        let offset = self.spawned;
+        for command in other.queue.drain(..) {
+            // Pending ids of `other` are relative to its own spawns, shift
+            // them so they keep pointing at the right slot after the merge.
+            self.queue.push(Box::new(move |world, spawned| {
+                let mut local = spawned.split_off(offset);
+                command(world, &mut local);
+                spawned.append(&mut local);
+            }));
+        }
+        self.spawned += std::mem::take(&mut other.spawned);
+        self
+    }
+
+    pub fn apply(&mut self, world: &mut World) -> Vec<EntityId> {
+        let mut spawned = Vec::with_capacity(self.spawned);
+        for command in self.queue.drain(..) {
+            command(world, &mut spawned);
+        }
+        self.spawned = 0;
+
+        spawned
+    }
+
+    fn push_for<F>(&mut self, target: CommandTarget, command: F) -> &mut Self
+    where
+        F: FnOnce(&mut World, EntityId) + 'static,
+    {
+        self.queue.push(Box::new(move |world, spawned| {
+            if let Some(entity_id) = resolve(target, spawned) {
+                command(world, entity_id);
+            }
+        }));
+        self
+    }
+}
+
+fn resolve(target: CommandTarget, spawned: &[EntityId]) -> Option<EntityId> {
+    match target {
+        CommandTarget::Entity(entity_id) => Some(entity_id),
+        CommandTarget::Pending(PendingEntity(idx)) => spawned.get(idx).copied(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ecs::component::{Name, Parent, Rotation};
+
+    #[test]
+    fn nested_appends_keep_pending_entities_apart() {
+        let mut outer = Commands::new();
+        let outer_first = outer.spawn((Name::new("outer"),));
+
+        let mut middle = Commands::new();
+        let middle_first = middle.spawn((Name::new("middle"),));
+        let mut inner = Commands::new();
+        let inner_child = inner.spawn((Name::new("inner child"),));
+        let inner_parent = inner.spawn((Name::new("inner parent"),));
+        inner
+            .set_parent(inner_child, inner_parent)
+            .add(inner_parent, Rotation { x: 1 });
+        middle.append(&mut inner);
+        middle.add(middle_first, Rotation { x: 2 });
+        outer.append(&mut middle);
+        assert!(inner.is_empty() && middle.is_empty());
+
+        let outer_second = outer.spawn((Name::new("outer second"),));
+        outer
+            .add(outer_second, Rotation { x: 3 })
+            .add(outer_first, Rotation { x: 4 });
+
+        let mut world = World::new();
+        let spawned = outer.apply(&mut world);
+        let names: Vec<_> = spawned.iter().map(|id| world.name(*id).unwrap()).collect();
+        assert_eq!(
+            names,
+            [
+                "outer",
+                "middle",
+                "inner child",
+                "inner parent",
+                "outer second"
+            ]
+        );
+        let rotations: Vec<_> = spawned
+            .iter()
+            .map(|id| world.fetch::<Rotation>(id).map(|rotation| rotation.x))
+            .collect();
+        assert_eq!(rotations, [Some(4), Some(2), None, Some(1), Some(3)]);
+        assert_eq!(
+            world.fetch::<Parent>(&spawned[2]).unwrap().entity,
+            spawned[3]
+        );
+    }
+}
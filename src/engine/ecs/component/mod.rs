@@ -1,94 +1,349 @@
-use super::world::{ComponentStorage, EntityId, World};
+use super::collision::{Manifold, RoundedConvex};
+use super::reflect::{FieldInfo, FieldValue, Reflect, ReflectError, ReflectValue};
+use super::spatial::Aabb;
+use super::world::EntityId;
 use crate::engine::render::Position as RenderPosition;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
-pub trait Component {}
+pub trait Component: 'static {}
 
+#[derive(Serialize, Deserialize)]
 pub struct Position {
     pub x: f32,
     pub y: f32,
     pub position_type: PositionType,
 }
-#[derive(PartialEq)]
+#[derive(PartialEq, Serialize, Deserialize)]
 pub enum PositionType {
     Abs,
     Rel,
 }
 impl Component for Position {}
-impl ComponentStorage<Position> for World {
-    fn add_component(&mut self, entity_id: EntityId, component: Position) -> &mut Self {
-        self.positions.insert(entity_id, component);
-
-        self
-    }
-
-    fn fetch(&self, entity_id: &EntityId) -> Option<&Position> {
-        self.positions.get(entity_id)
+crate::impl_reflect!(Position, "Position" {
+    "x" => x: f32,
+    "y" => y: f32,
+    "position_type" => position_type: PositionType,
+});
+
+// Optional human readable name, looked up with `World::find_by_name` and shown
+// in errors. Names are not required to be unique.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Name(pub String);
+impl Component for Name {}
+crate::impl_reflect!(Name, "Name" {
+    "value" => 0: String,
+});
+
+impl Name {
+    pub fn new(name: &str) -> Self {
+        Self(name.to_string())
     }
 
-    fn get_mut(&mut self, entity_id: &EntityId) -> Option<&mut Position> {
-        self.positions.get_mut(entity_id)
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Rotation {
     pub x: u16,
 }
 impl Component for Rotation {}
-impl ComponentStorage<Rotation> for World {
-    fn add_component(&mut self, entity_id: EntityId, component: Rotation) -> &mut Self {
-        self.rotation.insert(entity_id, component);
+crate::impl_reflect!(Rotation, "Rotation" {
+    "x" => x: u16,
+});
+
+// Local shape of a `Collider`, placed at the entity's absolute position plus
+// the collider offset. Boxes extend right and down from that point, the other
+// shapes are centered on it. `Obb`, `Capsule` and `Polygon` turn with the
+// entity's `Rotation`, in degrees clockwise on screen.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ColliderShape {
+    Aabb { width: usize, height: usize },
+    Obb { width: usize, height: usize },
+    Circle { radius: f32 },
+    // Vertical segment of `2 * half_height` grown by `radius`.
+    Capsule { half_height: f32, radius: f32 },
+    // Convex hull, points ordered around it.
+    Polygon { points: Vec<Vec2> },
+}
 
-        self
+// Two colliders only interact when each one's `layers` share a bit with the
+// other's `mask`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Collider {
+    pub offset: (isize, isize),
+    pub shape: ColliderShape,
+    // Layers this collider belongs to.
+    #[serde(default = "default_layers")]
+    pub layers: u32,
+    // Layers this collider hits.
+    #[serde(default = "default_mask")]
+    pub mask: u32,
+    // Sensors report overlaps without blocking anything.
+    #[serde(default)]
+    pub sensor: bool,
+}
+impl Component for Collider {}
+
+fn default_layers() -> u32 {
+    Collider::DEFAULT_LAYER
+}
+
+fn default_mask() -> u32 {
+    Collider::ALL_LAYERS
+}
+
+impl Collider {
+    pub const DEFAULT_LAYER: u32 = 1;
+    pub const ALL_LAYERS: u32 = u32::MAX;
+
+    pub fn aabb(offset: (isize, isize), size: (usize, usize)) -> Self {
+        Self::new(
+            offset,
+            ColliderShape::Aabb {
+                width: size.0,
+                height: size.1,
+            },
+        )
     }
 
-    fn fetch(&self, entity_id: &EntityId) -> Option<&Rotation> {
-        self.rotation.get(&entity_id)
+    pub fn obb(offset: (isize, isize), size: (usize, usize)) -> Self {
+        Self::new(
+            offset,
+            ColliderShape::Obb {
+                width: size.0,
+                height: size.1,
+            },
+        )
     }
 
-    fn get_mut(&mut self, entity_id: &EntityId) -> Option<&mut Rotation> {
-        self.rotation.get_mut(&entity_id)
+    pub fn circle(offset: (isize, isize), radius: f32) -> Self {
+        Self::new(offset, ColliderShape::Circle { radius })
     }
-}
 
-pub struct Collider {
-    pub offset: (isize, isize),
-    pub size: (usize, usize),
-}
-impl Component for Collider {}
-impl ComponentStorage<Collider> for World {
-    fn add_component(&mut self, entity_id: EntityId, component: Collider) -> &mut Self {
-        self.colliders.insert(entity_id, component);
+    pub fn capsule(offset: (isize, isize), half_height: f32, radius: f32) -> Self {
+        Self::new(
+            offset,
+            ColliderShape::Capsule {
+                half_height,
+                radius,
+            },
+        )
+    }
 
+    pub fn polygon(offset: (isize, isize), points: Vec<Vec2>) -> Self {
+        Self::new(offset, ColliderShape::Polygon { points })
+    }
+
+    pub fn new(offset: (isize, isize), shape: ColliderShape) -> Self {
+        Self {
+            offset,
+            shape,
+            layers: Self::DEFAULT_LAYER,
+            mask: Self::ALL_LAYERS,
+            sensor: false,
+        }
+    }
+
+    pub fn with_sensor(&mut self, sensor: bool) -> &mut Self {
+        self.sensor = sensor;
+        self
+    }
+
+    pub fn with_layers(&mut self, layers: u32, mask: u32) -> &mut Self {
+        self.layers = layers;
+        self.mask = mask;
         self
     }
 
-    fn fetch(&self, entity_id: &EntityId) -> Option<&Collider> {
-        self.colliders.get(entity_id)
+    pub fn interacts_with(&self, other: &Collider) -> bool {
+        self.layers & other.mask != 0 && other.layers & self.mask != 0
+    }
+
+    // Axis aligned bounds of the unrotated shape.
+    pub fn get_points(&self, entity_pos: RenderPosition) -> Aabb {
+        self.bounds(Vec2::new(entity_pos.0 as f32, entity_pos.1 as f32), 0.0)
+    }
+
+    // Whole pixel bounds, grown outwards to cover the shape.
+    pub fn bounds(&self, entity_pos: Vec2, rotation: f32) -> Aabb {
+        let (min, max) = match self.shape {
+            ColliderShape::Aabb { width, height } => {
+                let origin = self.origin(entity_pos);
+                (origin, origin + Vec2::new(width as f32, height as f32))
+            }
+            _ => self.to_convex(entity_pos, rotation).bounds(),
+        };
+
+        (
+            (min.x.floor() as isize, min.y.floor() as isize),
+            (max.x.ceil() as isize, max.y.ceil() as isize),
+        )
+    }
+
+    // World space shape for the narrowphase, `rotation` in degrees.
+    pub fn to_convex(&self, entity_pos: Vec2, rotation: f32) -> RoundedConvex {
+        let origin = self.origin(entity_pos);
+        let turn = Vec2::from_angle(rotation.to_radians());
+
+        match &self.shape {
+            ColliderShape::Aabb { width, height } => {
+                RoundedConvex::polygon(box_corners(origin, *width, *height).to_vec())
+            }
+            ColliderShape::Obb { width, height } => {
+                let center = origin + Vec2::new(*width as f32, *height as f32) / 2.0;
+                RoundedConvex::polygon(
+                    box_corners(origin, *width, *height)
+                        .iter()
+                        .map(|corner| center + turn.rotate(*corner - center))
+                        .collect(),
+                )
+            }
+            ColliderShape::Circle { radius } => RoundedConvex::circle(origin, *radius),
+            ColliderShape::Capsule {
+                half_height,
+                radius,
+            } => {
+                let half = turn.rotate(Vec2::new(0.0, *half_height));
+                RoundedConvex::capsule(origin - half, origin + half, *radius)
+            }
+            ColliderShape::Polygon { points } => RoundedConvex::polygon(
+                points
+                    .iter()
+                    .map(|point| origin + turn.rotate(*point))
+                    .collect(),
+            ),
+        }
     }
 
-    fn get_mut(&mut self, entity_id: &EntityId) -> Option<&mut Collider> {
-        self.colliders.get_mut(entity_id)
+    fn origin(&self, entity_pos: Vec2) -> Vec2 {
+        entity_pos + Vec2::new(self.offset.0 as f32, self.offset.1 as f32)
     }
 }
-enum ColliderShape {
-    Rectangle { width: usize, height: usize },
+
+fn box_corners(origin: Vec2, width: usize, height: usize) -> [Vec2; 4] {
+    let (width, height) = (width as f32, height as f32);
+    [
+        origin,
+        origin + Vec2::new(width, 0.0),
+        origin + Vec2::new(width, height),
+        origin + Vec2::new(0.0, height),
+    ]
 }
 
-impl Collider {
-    pub fn get_points(&self, entity_pos: RenderPosition) -> (RenderPosition, RenderPosition) {
-        let collider_position = (
-            entity_pos.0.wrapping_add_signed(self.offset.0),
-            entity_pos.1.wrapping_add_signed(self.offset.1),
-        );
+// Written by hand since the exposed fields depend on the shape.
+impl Reflect for Collider {
+    fn fields(&self) -> &'static [FieldInfo] {
+        const fn field(name: &'static str, type_name: &'static str) -> FieldInfo {
+            FieldInfo { name, type_name }
+        }
+        const OFFSET_X: FieldInfo = field("offset_x", isize::TYPE_NAME);
+        const OFFSET_Y: FieldInfo = field("offset_y", isize::TYPE_NAME);
+        const LAYERS: FieldInfo = field("layers", u32::TYPE_NAME);
+        const MASK: FieldInfo = field("mask", u32::TYPE_NAME);
+        const SENSOR: FieldInfo = field("sensor", bool::TYPE_NAME);
+        const BOX: &[FieldInfo] = &[
+            OFFSET_X,
+            OFFSET_Y,
+            LAYERS,
+            MASK,
+            SENSOR,
+            field("width", usize::TYPE_NAME),
+            field("height", usize::TYPE_NAME),
+        ];
+        const CIRCLE: &[FieldInfo] = &[
+            OFFSET_X,
+            OFFSET_Y,
+            LAYERS,
+            MASK,
+            SENSOR,
+            field("radius", f32::TYPE_NAME),
+        ];
+        const CAPSULE: &[FieldInfo] = &[
+            OFFSET_X,
+            OFFSET_Y,
+            LAYERS,
+            MASK,
+            SENSOR,
+            field("half_height", f32::TYPE_NAME),
+            field("radius", f32::TYPE_NAME),
+        ];
+        const POLYGON: &[FieldInfo] = &[OFFSET_X, OFFSET_Y, LAYERS, MASK, SENSOR];
+
+        match self.shape {
+            ColliderShape::Aabb { .. } | ColliderShape::Obb { .. } => BOX,
+            ColliderShape::Circle { .. } => CIRCLE,
+            ColliderShape::Capsule { .. } => CAPSULE,
+            ColliderShape::Polygon { .. } => POLYGON,
+        }
+    }
 
-        (
-            (collider_position.0, collider_position.1),
+    fn field(&self, name: &str) -> Option<ReflectValue> {
+        match (name, &self.shape) {
+            ("offset_x", _) => Some(self.offset.0.to_value()),
+            ("offset_y", _) => Some(self.offset.1.to_value()),
+            ("layers", _) => Some(self.layers.to_value()),
+            ("mask", _) => Some(self.mask.to_value()),
+            ("sensor", _) => Some(self.sensor.to_value()),
+            ("width", ColliderShape::Aabb { width, .. } | ColliderShape::Obb { width, .. }) => {
+                Some(width.to_value())
+            }
+            ("height", ColliderShape::Aabb { height, .. } | ColliderShape::Obb { height, .. }) => {
+                Some(height.to_value())
+            }
             (
-                collider_position.0 + self.size.0,
-                collider_position.1 + self.size.1,
-            ),
-        )
+                "radius",
+                ColliderShape::Circle { radius } | ColliderShape::Capsule { radius, .. },
+            ) => Some(radius.to_value()),
+            ("half_height", ColliderShape::Capsule { half_height, .. }) => {
+                Some(half_height.to_value())
+            }
+            _ => None,
+        }
+    }
+
+    fn set_field(&mut self, name: &str, value: ReflectValue) -> Result<(), ReflectError> {
+        fn set<T: FieldValue>(
+            target: &mut T,
+            name: &str,
+            value: ReflectValue,
+        ) -> Result<(), ReflectError> {
+            *target = T::from_value(&value).ok_or_else(|| ReflectError::TypeMismatch {
+                field: name.to_string(),
+                expected: T::TYPE_NAME,
+                found: value.clone(),
+            })?;
+            Ok(())
+        }
+
+        match (name, &mut self.shape) {
+            ("offset_x", _) => set(&mut self.offset.0, name, value),
+            ("offset_y", _) => set(&mut self.offset.1, name, value),
+            ("layers", _) => set(&mut self.layers, name, value),
+            ("mask", _) => set(&mut self.mask, name, value),
+            ("sensor", _) => set(&mut self.sensor, name, value),
+            ("width", ColliderShape::Aabb { width, .. } | ColliderShape::Obb { width, .. }) => {
+                set(width, name, value)
+            }
+            ("height", ColliderShape::Aabb { height, .. } | ColliderShape::Obb { height, .. }) => {
+                set(height, name, value)
+            }
+            (
+                "radius",
+                ColliderShape::Circle { radius } | ColliderShape::Capsule { radius, .. },
+            ) => set(radius, name, value),
+            ("half_height", ColliderShape::Capsule { half_height, .. }) => {
+                set(half_height, name, value)
+            }
+            _ => Err(ReflectError::UnknownField {
+                component: "Collider",
+                field: name.to_string(),
+            }),
+        }
     }
 }
 
@@ -98,21 +353,53 @@ pub struct CollisionInfo {
 }
 
 impl CollisionInfo {
-    pub fn add_collision(&mut self, entity_id: EntityId, collision_sides: HashSet<CollisionSide>) {
-        self.collision
-            .insert(entity_id, Collision::new(entity_id, collision_sides));
+    pub fn add_collision(&mut self, entity_id: EntityId, manifold: Manifold, sensor: bool) {
+        self.collision.insert(entity_id, Collision::new(entity_id, manifold, sensor));
+    }
+
+    // Collisions that should block movement, sensor overlaps left out.
+    pub fn solid(&self) -> impl Iterator<Item = &Collision> {
+        self.collision.values().filter(|collision| !collision.sensor)
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub struct Collision {
     pub entity_id: EntityId,
-    pub sides: HashSet<CollisionSide>,
+    // Normal points from the queried entity towards `entity_id`.
+    pub manifold: Manifold,
+    // Either collider is a sensor.
+    pub sensor: bool,
 }
 
 impl Collision {
-    pub fn new(entity_id: EntityId, sides: HashSet<CollisionSide>) -> Self {
-        Self { entity_id, sides }
+    pub fn new(entity_id: EntityId, manifold: Manifold, sensor: bool) -> Self {
+        Self {
+            entity_id,
+            manifold,
+            sensor,
+        }
+    }
+
+    // Box side of the queried entity facing `entity_id`, for callers written
+    // against the old side sets.
+    pub fn side(&self) -> Option<CollisionSide> {
+        self.manifold.side()
+    }
+
+    // Every side touched, a block in the corner of its container faces two.
+    pub fn sides(&self) -> HashSet<CollisionSide> {
+        self.manifold.sides()
+    }
+
+    // The queried entity lies fully inside `entity_id`.
+    pub fn contained(&self) -> bool {
+        self.manifold.first_inside
+    }
+
+    // `entity_id` lies fully inside the queried entity.
+    pub fn contains(&self) -> bool {
+        self.manifold.second_inside
     }
 }
 
@@ -122,77 +409,81 @@ pub enum CollisionSide {
     Right(usize),
     Top(usize),
     Bottom(usize),
-    Contained { x: usize, y: usize },
+    Contained,
 }
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct Children {
     entities: HashSet<EntityId>,
 }
 impl Component for Children {}
-impl ComponentStorage<Children> for World {
-    fn add_component(&mut self, entity_id: EntityId, component: Children) -> &mut Self {
-        self.children.insert(entity_id, component);
+impl Children {
+    pub fn entities(&self) -> &HashSet<EntityId> {
+        &self.entities
+    }
 
-        self
+    pub fn add_entity(&mut self, entity_id: EntityId) {
+        self.entities.insert(entity_id);
     }
 
-    fn fetch(&self, entity_id: &EntityId) -> Option<&Children> {
-        self.children.get(entity_id)
+    pub fn remove_entity(&mut self, entity_id: &EntityId) -> bool {
+        self.entities.remove(entity_id)
     }
 
-    fn get_mut(&mut self, entity_id: &EntityId) -> Option<&mut Children> {
-        self.children.get_mut(entity_id)
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
     }
 }
-impl Children {
+
+// Entities this entity never collides with. Either side listing the other is
+// enough to skip the pair.
+#[derive(Default, Serialize, Deserialize)]
+pub struct CollisionIgnore {
+    entities: HashSet<EntityId>,
+}
+impl Component for CollisionIgnore {}
+impl CollisionIgnore {
+    pub fn new(entities: impl IntoIterator<Item = EntityId>) -> Self {
+        Self {
+            entities: entities.into_iter().collect(),
+        }
+    }
+
     pub fn entities(&self) -> &HashSet<EntityId> {
         &self.entities
     }
 
+    pub fn contains(&self, entity_id: &EntityId) -> bool {
+        self.entities.contains(entity_id)
+    }
+
     pub fn add_entity(&mut self, entity_id: EntityId) {
         self.entities.insert(entity_id);
     }
-}
-
-pub struct Parent {
-    pub entity: EntityId,
-}
-impl Component for Parent {}
-impl ComponentStorage<Parent> for World {
-    fn add_component(&mut self, entity_id: EntityId, component: Parent) -> &mut Self {
-        self.parent.insert(entity_id, component);
 
-        self
+    pub fn remove_entity(&mut self, entity_id: &EntityId) -> bool {
+        self.entities.remove(entity_id)
     }
 
-    fn fetch(&self, entity_id: &EntityId) -> Option<&Parent> {
-        self.parent.get(entity_id)
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
     }
+}
 
-    fn get_mut(&mut self, entity_id: &EntityId) -> Option<&mut Parent> {
-        self.parent.get_mut(entity_id)
-    }
+#[derive(Serialize, Deserialize)]
+pub struct Parent {
+    pub entity: EntityId,
 }
+impl Component for Parent {}
 
+#[derive(Serialize, Deserialize)]
 pub struct Size {
     pub x: usize,
     pub y: usize,
 }
 
 impl Component for Size {}
-impl ComponentStorage<Size> for World {
-    fn add_component(&mut self, entity_id: EntityId, component: Size) -> &mut Self {
-        self.sizes.insert(entity_id, component);
-
-        self
-    }
-
-    fn fetch(&self, entity_id: &EntityId) -> Option<&Size> {
-        self.sizes.get(entity_id)
-    }
-
-    fn get_mut(&mut self, entity_id: &EntityId) -> Option<&mut Size> {
-        self.sizes.get_mut(entity_id)
-    }
-}
+crate::impl_reflect!(Size, "Size" {
+    "x" => x: usize,
+    "y" => y: usize,
+});
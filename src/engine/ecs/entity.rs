@@ -0,0 +1,179 @@
+use super::archetype::{EMPTY_ARCHETYPE, EntityLocation};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct EntityId {
+    index: u32,
+    generation: u32,
+}
+
+impl EntityId {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl Display for EntityId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+// Entity id together with its `Name`, if it has one, for error messages and
+// logs.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct EntityLabel {
+    pub id: EntityId,
+    pub name: Option<String>,
+}
+
+impl Display for EntityLabel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{name} ({})", self.id),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+struct EntitySlot {
+    generation: u32,
+    alive: bool,
+    location: EntityLocation,
+}
+
+// Allocator state, restoring it hands out exactly the same ids again.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EntitiesSnapshot {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+}
+
+// Slots are recycled through `free`, every reuse bumps the generation so ids
+// handed out for the previous occupant no longer compare equal.
+#[derive(Default)]
+pub(crate) struct Entities {
+    slots: Vec<EntitySlot>,
+    free: Vec<u32>,
+}
+
+impl Entities {
+    pub fn alloc(&mut self) -> EntityId {
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.generation = slot.generation.wrapping_add(1);
+            slot.alive = true;
+            slot.location = EntityLocation {
+                archetype: EMPTY_ARCHETYPE,
+                row: 0,
+            };
+
+            return EntityId {
+                index,
+                generation: slot.generation,
+            };
+        }
+
+        let index = self.slots.len() as u32;
+        self.slots.push(EntitySlot {
+            generation: 0,
+            alive: true,
+            location: EntityLocation {
+                archetype: EMPTY_ARCHETYPE,
+                row: 0,
+            },
+        });
+
+        EntityId {
+            index,
+            generation: 0,
+        }
+    }
+
+    pub fn free(&mut self, entity_id: EntityId) -> bool {
+        if !self.is_alive(entity_id) {
+            return false;
+        }
+
+        self.slots[entity_id.index as usize].alive = false;
+        self.free.push(entity_id.index);
+
+        true
+    }
+
+    pub fn is_alive(&self, entity_id: EntityId) -> bool {
+        self.slots
+            .get(entity_id.index as usize)
+            .is_some_and(|slot| slot.alive && slot.generation == entity_id.generation)
+    }
+
+    pub fn location(&self, entity_id: EntityId) -> Option<EntityLocation> {
+        self.slots
+            .get(entity_id.index as usize)
+            .filter(|slot| slot.alive && slot.generation == entity_id.generation)
+            .map(|slot| slot.location)
+    }
+
+    pub fn set_location(&mut self, entity_id: EntityId, location: EntityLocation) {
+        self.slots[entity_id.index as usize].location = location;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.alive)
+            .map(|(index, slot)| EntityId {
+                index: index as u32,
+                generation: slot.generation,
+            })
+    }
+
+    pub fn snapshot(&self) -> EntitiesSnapshot {
+        EntitiesSnapshot {
+            generations: self.slots.iter().map(|slot| slot.generation).collect(),
+            alive: self.slots.iter().map(|slot| slot.alive).collect(),
+            free: self.free.clone(),
+        }
+    }
+
+    // Locations are reset to the empty archetype, the caller places entities.
+    // Every free index must be a dead slot listed once, a duplicate would hand
+    // the same slot out twice.
+    pub fn restore(snapshot: &EntitiesSnapshot) -> Option<Self> {
+        let mut listed = vec![false; snapshot.alive.len()];
+        if snapshot.generations.len() != snapshot.alive.len()
+            || snapshot.free.iter().any(|&index| {
+                snapshot.alive.get(index as usize) != Some(&false)
+                    || std::mem::replace(&mut listed[index as usize], true)
+            })
+        {
+            return None;
+        }
+
+        let slots = snapshot
+            .generations
+            .iter()
+            .zip(&snapshot.alive)
+            .map(|(&generation, &alive)| EntitySlot {
+                generation,
+                alive,
+                location: EntityLocation {
+                    archetype: EMPTY_ARCHETYPE,
+                    row: 0,
+                },
+            })
+            .collect();
+
+        Some(Self {
+            slots,
+            free: snapshot.free.clone(),
+        })
+    }
+}
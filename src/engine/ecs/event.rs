@@ -0,0 +1,166 @@
+use super::world::World;
+use std::marker::PhantomData;
+
+struct EventInstance<E> {
+    id: usize,
+    event: E,
+}
+
+// Double buffered queue: events sent this frame go to `current`, on update
+// they move to `previous` and are dropped one update later. Readers that run
+// at least once per frame therefore never miss an event.
+pub struct Events<E: 'static> {
+    previous: Vec<EventInstance<E>>,
+    current: Vec<EventInstance<E>>,
+    event_count: usize,
+}
+
+impl<E: 'static> Default for Events<E> {
+    fn default() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            event_count: 0,
+        }
+    }
+}
+
+impl<E: 'static> Events<E> {
+    pub fn send(&mut self, event: E) {
+        self.current.push(EventInstance {
+            id: self.event_count,
+            event,
+        });
+        self.event_count += 1;
+    }
+
+    pub fn update(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &E> {
+        self.instances().map(|instance| &instance.event)
+    }
+
+    fn instances(&self) -> impl Iterator<Item = &EventInstance<E>> {
+        self.previous.iter().chain(self.current.iter())
+    }
+}
+
+pub struct EventWriter<'w, E: 'static> {
+    events: &'w mut Events<E>,
+}
+
+impl<'w, E: 'static> EventWriter<'w, E> {
+    pub fn new(world: &'w mut World) -> Self {
+        Self {
+            events: world.add_event::<E>(),
+        }
+    }
+
+    pub fn send(&mut self, event: E) -> &mut Self {
+        self.events.send(event);
+        self
+    }
+
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = E>) -> &mut Self {
+        for event in events {
+            self.events.send(event);
+        }
+        self
+    }
+}
+
+pub struct EventReader<E: 'static> {
+    last_event_count: usize,
+    _marker: PhantomData<fn() -> E>,
+}
+
+impl<E: 'static> Default for EventReader<E> {
+    fn default() -> Self {
+        Self {
+            last_event_count: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<E: 'static> EventReader<E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read<'a>(&mut self, world: &'a World) -> impl Iterator<Item = &'a E> + use<'a, E> {
+        let cursor = self.last_event_count;
+        let events = world.resource::<Events<E>>();
+        if let Some(events) = events {
+            self.last_event_count = events.event_count;
+        }
+
+        events
+            .into_iter()
+            .flat_map(|events| events.instances())
+            .filter(move |instance| instance.id >= cursor)
+            .map(|instance| &instance.event)
+    }
+
+    pub fn clear(&mut self, world: &World) {
+        if let Some(events) = world.resource::<Events<E>>() {
+            self.last_event_count = events.event_count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(reader: &mut EventReader<u32>, world: &World) -> Vec<u32> {
+        reader.read(world).copied().collect()
+    }
+
+    #[test]
+    fn events_expire_after_two_updates() {
+        let mut world = World::new();
+        EventWriter::<u32>::new(&mut world).send(1);
+
+        world.update_events();
+        assert_eq!(read(&mut EventReader::new(), &world), [1]);
+        EventWriter::<u32>::new(&mut world).send(2);
+        world.update_events();
+        assert_eq!(read(&mut EventReader::new(), &world), [2]);
+        world.update_events();
+        assert!(read(&mut EventReader::new(), &world).is_empty());
+        assert!(world.resource::<Events<u32>>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn readers_keep_their_own_cursor() {
+        let mut world = World::new();
+        let mut early = EventReader::<u32>::new();
+        let mut skipping = EventReader::<u32>::new();
+        EventWriter::<u32>::new(&mut world).send_batch([1, 2]);
+        assert_eq!(read(&mut early, &world), [1, 2]);
+        assert!(read(&mut early, &world).is_empty());
+        skipping.clear(&world);
+
+        EventWriter::<u32>::new(&mut world).send(3);
+        world.update_events();
+        assert_eq!(read(&mut early, &world), [3]);
+        assert_eq!(read(&mut skipping, &world), [3]);
+        assert_eq!(read(&mut EventReader::new(), &world), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,40 @@
+use super::command::Commands;
+use super::world::{EntityId, World};
+use std::any::TypeId;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Lifecycle {
+    // Component inserted on an entity that did not have it.
+    Added,
+    // Component inserted over an existing value, fires after the new value
+    // is stored.
+    Replaced,
+    // Component about to be removed, fires while the value is still readable.
+    Removed,
+}
+
+// Hooks only see the world immutably, structural changes go through the
+// command buffer which is applied once the outermost triggering operation returns.
+pub type Hook = Box<dyn Fn(&World, EntityId, &mut Commands)>;
+
+#[derive(Default)]
+pub struct Hooks {
+    hooks: HashMap<(TypeId, Lifecycle), Vec<Hook>>,
+}
+
+impl Hooks {
+    pub fn register(&mut self, type_id: TypeId, lifecycle: Lifecycle, hook: Hook) {
+        self.hooks.entry((type_id, lifecycle)).or_default().push(hook);
+    }
+
+    pub fn get(&self, type_id: TypeId, lifecycle: Lifecycle) -> &[Hook] {
+        self.hooks
+            .get(&(type_id, lifecycle))
+            .map_or(&[], |hooks| hooks.as_slice())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+}
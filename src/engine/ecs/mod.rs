@@ -1,11 +1,37 @@
 use thiserror::Error;
+use crate::engine::ecs::entity::EntityLabel;
 use crate::engine::ecs::world::EntityId;
 
 pub mod world;
+pub mod entity;
 pub mod component;
+pub mod storage;
+pub mod archetype;
+pub mod query;
+pub mod schedule;
+pub mod bundle;
+pub mod builder;
+pub mod command;
+pub mod event;
+pub mod transform;
+pub mod snapshot;
+pub mod scene;
+pub mod prefab;
+pub mod reflect;
+pub mod hooks;
+pub mod spatial;
+pub mod collision;
 
 #[derive(Error, Debug)]
 pub enum ComponentError {
     #[error("component {0} is not attached to entity {1}")]
-    NotAttached(String, EntityId)
+    NotAttached(String, EntityLabel),
+    #[error("entity {0} is not alive")]
+    DeadEntity(EntityId),
+    #[error("component {0} already has stored values, its storage type can no longer change")]
+    StorageInUse(String),
+    #[error("hierarchy of entity {0} contains a cycle")]
+    HierarchyCycle(EntityLabel),
+    #[error("parent of entity {0} is not alive or does not list it as a child")]
+    DanglingParent(EntityLabel),
 }
\ No newline at end of file
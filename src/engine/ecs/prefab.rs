@@ -0,0 +1,142 @@
+use super::component::Component;
+use super::scene::{Scene, SceneError, SceneInstance};
+use super::snapshot::ComponentInsert;
+use super::world::{ComponentStorage, EntityId, World};
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PrefabError {
+    #[error("prefab {0} is not registered")]
+    NotFound(String),
+    #[error("prefab must have exactly one root entity, found {0}")]
+    RootCount(usize),
+    #[error("prefab has no entity named {0}")]
+    UnknownEntity(String),
+    #[error(transparent)]
+    Scene(#[from] SceneError),
+}
+
+// Entity tree stored as a scene with a single root, instantiated on demand.
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    scene: Scene,
+    root: usize,
+}
+
+impl Prefab {
+    pub fn new(scene: Scene) -> Result<Self, PrefabError> {
+        let roots: Vec<usize> = scene
+            .entities
+            .iter()
+            .enumerate()
+            .filter(|(_, entity)| entity.parent.is_none())
+            .map(|(idx, _)| idx)
+            .collect();
+        match roots[..] {
+            [root] => Ok(Self { scene, root }),
+            _ => Err(PrefabError::RootCount(roots.len())),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, PrefabError> {
+        Self::new(Scene::from_json(json)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PrefabError> {
+        Self::new(Scene::load(path)?)
+    }
+
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    // Overrides replace the template's value of the same component, or add
+    // the component when the template does not have it.
+    pub fn instantiate(
+        &self,
+        world: &mut World,
+        overrides: PrefabOverrides,
+    ) -> Result<PrefabInstance, PrefabError> {
+        let mut targets = Vec::with_capacity(overrides.inserts.len());
+        for (entity, insert) in overrides.inserts {
+            let idx = match entity {
+                None => self.root,
+                Some(name) => self
+                    .scene
+                    .entities
+                    .iter()
+                    .position(|entity| entity.name.as_deref() == Some(name.as_str()))
+                    .ok_or(PrefabError::UnknownEntity(name))?,
+            };
+            targets.push((idx, insert));
+        }
+
+        let scene = self.scene.spawn(world)?;
+        for (idx, insert) in targets {
+            insert(world, scene.entities[idx]);
+        }
+
+        Ok(PrefabInstance {
+            root: scene.entities[self.root],
+            scene,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct PrefabOverrides {
+    inserts: Vec<(Option<String>, ComponentInsert)>,
+}
+
+impl PrefabOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<T: Component>(self, component: T) -> Self {
+        self.push(None, component)
+    }
+
+    pub fn with_on<T: Component>(self, entity: &str, component: T) -> Self {
+        self.push(Some(entity.to_string()), component)
+    }
+
+    fn push<T: Component>(mut self, entity: Option<String>, component: T) -> Self {
+        self.inserts.push((
+            entity,
+            Box::new(move |world: &mut World, entity_id| {
+                world.add_component(entity_id, component);
+            }),
+        ));
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct PrefabInstance {
+    pub root: EntityId,
+    pub scene: SceneInstance,
+}
+
+// World resource holding every registered prefab by name.
+#[derive(Default)]
+pub struct Prefabs {
+    prefabs: HashMap<String, Rc<Prefab>>,
+}
+
+impl Prefabs {
+    pub fn insert(&mut self, name: &str, prefab: Prefab) -> Option<Rc<Prefab>> {
+        self.prefabs.insert(name.to_string(), Rc::new(prefab))
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<Prefab>> {
+        self.prefabs.get(name).cloned()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.prefabs.contains_key(name)
+    }
+}
@@ -0,0 +1,991 @@
+use super::archetype::{Archetype, ArchetypeId};
+use super::component::Component;
+use super::spatial::SpatialGrid;
+use super::storage::{ComponentTicks, SparseSet, Tick};
+use super::world::{EntityId, World};
+use std::any::{Any, TypeId, type_name};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+#[derive(Default)]
+pub struct Access {
+    reads: Vec<(TypeId, &'static str)>,
+    writes: Vec<(TypeId, &'static str)>,
+}
+
+impl Access {
+    pub fn add_read<T: Component>(&mut self) {
+        let type_id = TypeId::of::<T>();
+        if self.writes.iter().any(|(id, _)| *id == type_id) {
+            panic!("query reads {} while also writing it", type_name::<T>());
+        }
+        self.reads.push((type_id, type_name::<T>()));
+    }
+
+    pub fn add_write<T: Component>(&mut self) {
+        let type_id = TypeId::of::<T>();
+        if self
+            .reads
+            .iter()
+            .chain(self.writes.iter())
+            .any(|(id, _)| *id == type_id)
+        {
+            panic!("query writes {} more than once", type_name::<T>());
+        }
+        self.writes.push((type_id, type_name::<T>()));
+    }
+}
+
+/// Shared matching logic of query data and filters. Whole archetypes are
+/// accepted or rejected first, `set_archetype` then resolves the columns that
+/// rows are read from.
+pub trait WorldQuery {
+    type State: Copy;
+    type Fetch: Copy;
+
+    fn init_state(world: &mut World, fetches: &mut SparseFetches) -> Self::State;
+    fn matches_archetype(state: &Self::State, archetype: &Archetype) -> bool;
+
+    fn requires(_state: &Self::State) -> Requires {
+        Requires::Nothing
+    }
+
+    /// # Safety
+    /// The archetype must pass `matches_archetype` and must not be modified
+    /// structurally while the returned fetch is in use.
+    unsafe fn set_archetype(state: &Self::State, archetype: &mut Archetype) -> Self::Fetch;
+
+    /// # Safety
+    /// `fetch` must come from `set_archetype` and `row` must be in bounds.
+    unsafe fn matches_row(_fetch: &Self::Fetch, _entity_id: EntityId, _row: usize) -> bool {
+        true
+    }
+}
+
+/// Describes what a query yields per entity. Implemented for `EntityId`,
+/// `&T`, `&mut T` (yielding `Mut<T>`), `Option<Q>` and tuples of those.
+pub trait QueryData: WorldQuery {
+    type Item<'w>;
+
+    fn access(access: &mut Access);
+
+    /// # Safety
+    /// The row must pass `matches_row` and no other item for the same entity
+    /// may be alive while this one is.
+    unsafe fn fetch<'w>(fetch: &Self::Fetch, entity_id: EntityId, row: usize) -> Self::Item<'w>;
+}
+
+pub trait QueryFilter: WorldQuery {}
+
+/// Components a query term needs an entity to have. When every required
+/// component lives in a sparse set the query walks the smallest set's
+/// entities instead of every row of every archetype.
+#[derive(Clone, Copy)]
+pub enum Requires {
+    Nothing,
+    Table,
+    Sparse(*const [EntityId]),
+}
+
+impl Requires {
+    fn and(self, other: Self) -> Self {
+        match (self, other) {
+            (Requires::Table, _) | (_, Requires::Table) => Requires::Table,
+            (Requires::Nothing, other) | (other, Requires::Nothing) => other,
+            (Requires::Sparse(a), Requires::Sparse(b)) => {
+                Requires::Sparse(if a.len() <= b.len() { a } else { b })
+            }
+        }
+    }
+}
+
+// Where the rows of one component type are read from. Table fetches are
+// indexed by archetype row, sparse set fetches look the entity up.
+pub struct ComponentFetch<T: Component> {
+    data: *mut T,
+    ticks: *mut ComponentTicks,
+    sparse_set: Option<*const SparseSet<T>>,
+}
+
+// Sparse set fetches taken while a query is set up. A type that is both read
+// and filtered on shares one fetch, borrowing its set again would invalidate
+// the pointers taken first.
+#[derive(Default)]
+pub struct SparseFetches(Vec<(TypeId, Box<dyn Any>)>);
+
+impl SparseFetches {
+    fn get<T: Component>(&self) -> Option<ComponentFetch<T>> {
+        self.0
+            .iter()
+            .find(|(type_id, _)| *type_id == TypeId::of::<T>())
+            .and_then(|(_, fetch)| fetch.downcast_ref::<ComponentFetch<T>>())
+            .copied()
+    }
+}
+
+impl<T: Component> Clone for ComponentFetch<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Component> Copy for ComponentFetch<T> {}
+
+impl<T: Component> ComponentFetch<T> {
+    // `Some` for sparse set components, the fetch stays valid for every
+    // archetype.
+    fn init(world: &mut World, fetches: &mut SparseFetches) -> Option<Self> {
+        if let Some(fetch) = fetches.get::<T>() {
+            return Some(fetch);
+        }
+
+        let set = world.sparse_set_mut::<T>()?;
+        let data = set.data_ptr();
+        let ticks = set.ticks_ptr();
+        let fetch = Self {
+            data,
+            ticks,
+            sparse_set: Some(&*set),
+        };
+        fetches.0.push((TypeId::of::<T>(), Box::new(fetch)));
+
+        Some(fetch)
+    }
+
+    fn matches_archetype(state: &Option<Self>, archetype: &Archetype) -> bool {
+        state.is_some() || archetype.contains(TypeId::of::<T>())
+    }
+
+    fn requires(state: &Option<Self>) -> Requires {
+        match state.and_then(|fetch| fetch.sparse_set) {
+            // The set outlives the query, which holds the world mutably.
+            Some(set) => Requires::Sparse(unsafe { (*set).entities() }),
+            None => Requires::Table,
+        }
+    }
+
+    unsafe fn set_archetype(state: &Option<Self>, archetype: &mut Archetype) -> Self {
+        if let Some(fetch) = state {
+            return *fetch;
+        }
+
+        let column = archetype.column_mut::<T>().unwrap();
+        Self {
+            data: column.data_ptr(),
+            ticks: column.ticks_ptr(),
+            sparse_set: None,
+        }
+    }
+
+    unsafe fn index(&self, entity_id: EntityId, row: usize) -> Option<usize> {
+        match self.sparse_set {
+            Some(set) => unsafe { (*set).dense_index(entity_id) },
+            None => Some(row),
+        }
+    }
+
+    unsafe fn ticks(&self, entity_id: EntityId, row: usize) -> Option<ComponentTicks> {
+        let idx = unsafe { self.index(entity_id, row) }?;
+        Some(unsafe { *self.ticks.add(idx) })
+    }
+}
+
+impl WorldQuery for EntityId {
+    type State = ();
+    type Fetch = ();
+
+    fn init_state(_world: &mut World, _fetches: &mut SparseFetches) -> Self::State {}
+
+    fn matches_archetype(_state: &Self::State, _archetype: &Archetype) -> bool {
+        true
+    }
+
+    unsafe fn set_archetype(_state: &Self::State, _archetype: &mut Archetype) -> Self::Fetch {}
+}
+
+impl QueryData for EntityId {
+    type Item<'w> = EntityId;
+
+    fn access(_access: &mut Access) {}
+
+    unsafe fn fetch<'w>(_fetch: &Self::Fetch, entity_id: EntityId, _row: usize) -> Self::Item<'w> {
+        entity_id
+    }
+}
+
+impl<T: Component> WorldQuery for &T {
+    type State = Option<ComponentFetch<T>>;
+    type Fetch = ComponentFetch<T>;
+
+    fn init_state(world: &mut World, fetches: &mut SparseFetches) -> Self::State {
+        ComponentFetch::init(world, fetches)
+    }
+
+    fn matches_archetype(state: &Self::State, archetype: &Archetype) -> bool {
+        ComponentFetch::matches_archetype(state, archetype)
+    }
+
+    fn requires(state: &Self::State) -> Requires {
+        ComponentFetch::requires(state)
+    }
+
+    unsafe fn set_archetype(state: &Self::State, archetype: &mut Archetype) -> Self::Fetch {
+        unsafe { ComponentFetch::set_archetype(state, archetype) }
+    }
+
+    unsafe fn matches_row(fetch: &Self::Fetch, entity_id: EntityId, row: usize) -> bool {
+        unsafe { fetch.index(entity_id, row) }.is_some()
+    }
+}
+
+impl<T: Component> QueryData for &T {
+    type Item<'w> = &'w T;
+
+    fn access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+
+    unsafe fn fetch<'w>(fetch: &Self::Fetch, entity_id: EntityId, row: usize) -> Self::Item<'w> {
+        unsafe {
+            let idx = fetch.index(entity_id, row).unwrap();
+            &*fetch.data.add(idx)
+        }
+    }
+}
+
+/// Write access to one component, handed out by `&mut T` queries and
+/// `World::get_mut`. Only writing through it marks the component changed and,
+/// for components the broadphase indexes, queues the entity for re-indexing.
+pub struct Mut<'w, T: Component> {
+    value: &'w mut T,
+    ticks: &'w mut ComponentTicks,
+    change_tick: Tick,
+    spatial: Option<(&'w RefCell<SpatialGrid>, EntityId)>,
+}
+
+impl<'w, T: Component> Mut<'w, T> {
+    pub(crate) fn new(
+        value: &'w mut T,
+        ticks: &'w mut ComponentTicks,
+        change_tick: Tick,
+        spatial: Option<(&'w RefCell<SpatialGrid>, EntityId)>,
+    ) -> Self {
+        Self {
+            value,
+            ticks,
+            change_tick,
+            spatial,
+        }
+    }
+
+    pub fn set_changed(&mut self) {
+        self.ticks.changed = self.change_tick;
+        if let Some((spatial, entity_id)) = self.spatial.take() {
+            spatial.borrow_mut().mark_entity_dirty(entity_id);
+        }
+    }
+
+    // Plain reference for callers that outlive the guard, counts as a write.
+    pub fn into_inner(mut self) -> &'w mut T {
+        self.set_changed();
+        self.value
+    }
+}
+
+impl<T: Component> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T: Component> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.set_changed();
+        self.value
+    }
+}
+
+impl<T: Component + std::fmt::Debug> std::fmt::Debug for Mut<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<T: Component> WorldQuery for &mut T {
+    type State = (
+        Option<ComponentFetch<T>>,
+        Tick,
+        Option<*const RefCell<SpatialGrid>>,
+    );
+    type Fetch = (
+        ComponentFetch<T>,
+        Tick,
+        Option<*const RefCell<SpatialGrid>>,
+    );
+
+    fn init_state(world: &mut World, fetches: &mut SparseFetches) -> Self::State {
+        (
+            ComponentFetch::init(world, fetches),
+            world.change_tick(),
+            world.spatial_for(TypeId::of::<T>()),
+        )
+    }
+
+    fn matches_archetype(state: &Self::State, archetype: &Archetype) -> bool {
+        ComponentFetch::matches_archetype(&state.0, archetype)
+    }
+
+    fn requires(state: &Self::State) -> Requires {
+        ComponentFetch::requires(&state.0)
+    }
+
+    unsafe fn set_archetype(state: &Self::State, archetype: &mut Archetype) -> Self::Fetch {
+        let fetch = unsafe { ComponentFetch::set_archetype(&state.0, archetype) };
+        (fetch, state.1, state.2)
+    }
+
+    unsafe fn matches_row(fetch: &Self::Fetch, entity_id: EntityId, row: usize) -> bool {
+        unsafe { fetch.0.index(entity_id, row) }.is_some()
+    }
+}
+
+impl<T: Component> QueryData for &mut T {
+    type Item<'w> = Mut<'w, T>;
+
+    fn access(access: &mut Access) {
+        access.add_write::<T>();
+    }
+
+    unsafe fn fetch<'w>(fetch: &Self::Fetch, entity_id: EntityId, row: usize) -> Self::Item<'w> {
+        let (fetch, tick, spatial) = fetch;
+        unsafe {
+            let idx = fetch.index(entity_id, row).unwrap();
+            Mut::new(
+                &mut *fetch.data.add(idx),
+                &mut *fetch.ticks.add(idx),
+                *tick,
+                spatial.map(|spatial| (&*spatial, entity_id)),
+            )
+        }
+    }
+}
+
+impl<Q: WorldQuery> WorldQuery for Option<Q> {
+    type State = Q::State;
+    type Fetch = Option<Q::Fetch>;
+
+    fn init_state(world: &mut World, fetches: &mut SparseFetches) -> Self::State {
+        Q::init_state(world, fetches)
+    }
+
+    fn matches_archetype(_state: &Self::State, _archetype: &Archetype) -> bool {
+        true
+    }
+
+    unsafe fn set_archetype(state: &Self::State, archetype: &mut Archetype) -> Self::Fetch {
+        if Q::matches_archetype(state, archetype) {
+            Some(unsafe { Q::set_archetype(state, archetype) })
+        } else {
+            None
+        }
+    }
+}
+
+impl<Q: QueryData> QueryData for Option<Q> {
+    type Item<'w> = Option<Q::Item<'w>>;
+
+    fn access(access: &mut Access) {
+        Q::access(access);
+    }
+
+    unsafe fn fetch<'w>(fetch: &Self::Fetch, entity_id: EntityId, row: usize) -> Self::Item<'w> {
+        match fetch {
+            Some(fetch) if unsafe { Q::matches_row(fetch, entity_id, row) } => {
+                Some(unsafe { Q::fetch(fetch, entity_id, row) })
+            }
+            _ => None,
+        }
+    }
+}
+
+pub struct With<T: Component>(PhantomData<T>);
+pub struct Without<T: Component>(PhantomData<T>);
+pub struct Added<T: Component>(PhantomData<T>);
+pub struct Changed<T: Component>(PhantomData<T>);
+
+impl<T: Component> WorldQuery for With<T> {
+    type State = Option<ComponentFetch<T>>;
+    type Fetch = ComponentFetch<T>;
+
+    fn init_state(world: &mut World, fetches: &mut SparseFetches) -> Self::State {
+        ComponentFetch::init(world, fetches)
+    }
+
+    fn matches_archetype(state: &Self::State, archetype: &Archetype) -> bool {
+        ComponentFetch::matches_archetype(state, archetype)
+    }
+
+    fn requires(state: &Self::State) -> Requires {
+        ComponentFetch::requires(state)
+    }
+
+    unsafe fn set_archetype(state: &Self::State, archetype: &mut Archetype) -> Self::Fetch {
+        unsafe { ComponentFetch::set_archetype(state, archetype) }
+    }
+
+    unsafe fn matches_row(fetch: &Self::Fetch, entity_id: EntityId, row: usize) -> bool {
+        unsafe { fetch.index(entity_id, row) }.is_some()
+    }
+}
+
+impl<T: Component> WorldQuery for Without<T> {
+    type State = Option<ComponentFetch<T>>;
+    type Fetch = Option<ComponentFetch<T>>;
+
+    fn init_state(world: &mut World, fetches: &mut SparseFetches) -> Self::State {
+        ComponentFetch::init(world, fetches)
+    }
+
+    fn matches_archetype(state: &Self::State, archetype: &Archetype) -> bool {
+        state.is_some() || !archetype.contains(TypeId::of::<T>())
+    }
+
+    unsafe fn set_archetype(state: &Self::State, _archetype: &mut Archetype) -> Self::Fetch {
+        *state
+    }
+
+    unsafe fn matches_row(fetch: &Self::Fetch, entity_id: EntityId, row: usize) -> bool {
+        fetch.is_none_or(|fetch| unsafe { fetch.index(entity_id, row) }.is_none())
+    }
+}
+
+impl<T: Component> WorldQuery for Added<T> {
+    type State = (Option<ComponentFetch<T>>, Tick);
+    type Fetch = (ComponentFetch<T>, Tick);
+
+    fn init_state(world: &mut World, fetches: &mut SparseFetches) -> Self::State {
+        (
+            ComponentFetch::init(world, fetches),
+            world.last_change_tick(),
+        )
+    }
+
+    fn matches_archetype(state: &Self::State, archetype: &Archetype) -> bool {
+        ComponentFetch::matches_archetype(&state.0, archetype)
+    }
+
+    fn requires(state: &Self::State) -> Requires {
+        ComponentFetch::requires(&state.0)
+    }
+
+    unsafe fn set_archetype(state: &Self::State, archetype: &mut Archetype) -> Self::Fetch {
+        (unsafe { ComponentFetch::set_archetype(&state.0, archetype) }, state.1)
+    }
+
+    unsafe fn matches_row(fetch: &Self::Fetch, entity_id: EntityId, row: usize) -> bool {
+        let (fetch, last_tick) = fetch;
+        unsafe { fetch.ticks(entity_id, row) }.is_some_and(|ticks| ticks.is_added(*last_tick))
+    }
+}
+
+impl<T: Component> WorldQuery for Changed<T> {
+    type State = (Option<ComponentFetch<T>>, Tick);
+    type Fetch = (ComponentFetch<T>, Tick);
+
+    fn init_state(world: &mut World, fetches: &mut SparseFetches) -> Self::State {
+        (
+            ComponentFetch::init(world, fetches),
+            world.last_change_tick(),
+        )
+    }
+
+    fn matches_archetype(state: &Self::State, archetype: &Archetype) -> bool {
+        ComponentFetch::matches_archetype(&state.0, archetype)
+    }
+
+    fn requires(state: &Self::State) -> Requires {
+        ComponentFetch::requires(&state.0)
+    }
+
+    unsafe fn set_archetype(state: &Self::State, archetype: &mut Archetype) -> Self::Fetch {
+        (unsafe { ComponentFetch::set_archetype(&state.0, archetype) }, state.1)
+    }
+
+    unsafe fn matches_row(fetch: &Self::Fetch, entity_id: EntityId, row: usize) -> bool {
+        let (fetch, last_tick) = fetch;
+        unsafe { fetch.ticks(entity_id, row) }.is_some_and(|ticks| ticks.is_changed(*last_tick))
+    }
+}
+
+impl<T: Component> QueryFilter for With<T> {}
+impl<T: Component> QueryFilter for Without<T> {}
+impl<T: Component> QueryFilter for Added<T> {}
+impl<T: Component> QueryFilter for Changed<T> {}
+
+impl WorldQuery for () {
+    type State = ();
+    type Fetch = ();
+
+    fn init_state(_world: &mut World, _fetches: &mut SparseFetches) -> Self::State {}
+
+    fn matches_archetype(_state: &Self::State, _archetype: &Archetype) -> bool {
+        true
+    }
+
+    unsafe fn set_archetype(_state: &Self::State, _archetype: &mut Archetype) -> Self::Fetch {}
+}
+
+impl QueryFilter for () {}
+
+macro_rules! impl_query_tuple {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($name: WorldQuery),*> WorldQuery for ($($name,)*) {
+            type State = ($($name::State,)*);
+            type Fetch = ($($name::Fetch,)*);
+
+            fn init_state(world: &mut World, fetches: &mut SparseFetches) -> Self::State {
+                ($($name::init_state(world, fetches),)*)
+            }
+
+            fn matches_archetype(state: &Self::State, archetype: &Archetype) -> bool {
+                let ($($name,)*) = state;
+                true $(&& $name::matches_archetype($name, archetype))*
+            }
+
+            fn requires(state: &Self::State) -> Requires {
+                let ($($name,)*) = state;
+                Requires::Nothing $(.and($name::requires($name)))*
+            }
+
+            unsafe fn set_archetype(state: &Self::State, archetype: &mut Archetype) -> Self::Fetch {
+                let ($($name,)*) = state;
+                unsafe { ($($name::set_archetype($name, archetype),)*) }
+            }
+
+            unsafe fn matches_row(fetch: &Self::Fetch, entity_id: EntityId, row: usize) -> bool {
+                let ($($name,)*) = fetch;
+                true $(&& unsafe { $name::matches_row($name, entity_id, row) })*
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl<$($name: QueryData),*> QueryData for ($($name,)*) {
+            type Item<'w> = ($($name::Item<'w>,)*);
+
+            fn access(access: &mut Access) {
+                $($name::access(access);)*
+            }
+
+            unsafe fn fetch<'w>(fetch: &Self::Fetch, entity_id: EntityId, row: usize) -> Self::Item<'w> {
+                let ($($name,)*) = fetch;
+                unsafe { ($($name::fetch($name, entity_id, row),)*) }
+            }
+        }
+
+        impl<$($name: QueryFilter),*> QueryFilter for ($($name,)*) {}
+    };
+}
+
+impl_query_tuple!(A);
+impl_query_tuple!(A, B);
+impl_query_tuple!(A, B, C);
+impl_query_tuple!(A, B, C, D);
+impl_query_tuple!(A, B, C, D, E);
+impl_query_tuple!(A, B, C, D, E, F);
+impl_query_tuple!(A, B, C, D, E, F, G);
+impl_query_tuple!(A, B, C, D, E, F, G, H);
+
+struct MatchedArchetype<Q: QueryData, F: QueryFilter> {
+    id: ArchetypeId,
+    fetch: Q::Fetch,
+    filter: F::Fetch,
+    entities: *const EntityId,
+    len: usize,
+}
+
+pub struct QueryIter<'w, Q: QueryData, F: QueryFilter> {
+    archetypes: Vec<MatchedArchetype<Q, F>>,
+    // Matched archetype and row of each entity in the smallest required
+    // sparse set, walked instead of `archetypes` when there is one.
+    sparse_rows: Option<Vec<(usize, usize)>>,
+    current: usize,
+    row: usize,
+    _world: PhantomData<&'w mut World>,
+}
+
+impl<'w, Q: QueryData, F: QueryFilter> QueryIter<'w, Q, F> {
+    pub(crate) fn new(world: &'w mut World) -> Self {
+        let mut access = Access::default();
+        Q::access(&mut access);
+
+        let mut fetches = SparseFetches::default();
+        let state = Q::init_state(world, &mut fetches);
+        let filter_state = F::init_state(world, &mut fetches);
+        let archetypes: Vec<MatchedArchetype<Q, F>> = world
+            .archetypes_mut()
+            .iter_mut()
+            .filter(|archetype| {
+                !archetype.is_empty()
+                    && Q::matches_archetype(&state, archetype)
+                    && F::matches_archetype(&filter_state, archetype)
+            })
+            .map(|archetype| MatchedArchetype {
+                id: archetype.id(),
+                // The world stays mutably borrowed for 'w, so no archetype can
+                // be resized while these pointers are alive.
+                fetch: unsafe { Q::set_archetype(&state, archetype) },
+                filter: unsafe { F::set_archetype(&filter_state, archetype) },
+                entities: archetype.entities().as_ptr(),
+                len: archetype.len(),
+            })
+            .collect();
+
+        let sparse_rows = match Q::requires(&state).and(F::requires(&filter_state)) {
+            Requires::Sparse(entities) => {
+                let matched: HashMap<ArchetypeId, usize> = archetypes
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, archetype)| (archetype.id, idx))
+                    .collect();
+                // Sparse sets are not resized while the world is borrowed.
+                let entities = unsafe { &*entities };
+                Some(
+                    entities
+                        .iter()
+                        .filter_map(|entity_id| {
+                            let location = world.entity_location(*entity_id)?;
+                            Some((*matched.get(&location.archetype)?, location.row))
+                        })
+                        .collect(),
+                )
+            }
+            Requires::Nothing | Requires::Table => None,
+        };
+
+        Self {
+            archetypes,
+            sparse_rows,
+            current: 0,
+            row: 0,
+            _world: PhantomData,
+        }
+    }
+
+    fn fetch_row(archetype: &MatchedArchetype<Q, F>, row: usize) -> Option<Q::Item<'w>> {
+        // Every row is yielded once and `Access` rejects aliasing component
+        // types, so the returned references never overlap.
+        unsafe {
+            let entity_id = *archetype.entities.add(row);
+            (Q::matches_row(&archetype.fetch, entity_id, row)
+                && F::matches_row(&archetype.filter, entity_id, row))
+            .then(|| Q::fetch(&archetype.fetch, entity_id, row))
+        }
+    }
+}
+
+impl<'w, Q: QueryData, F: QueryFilter> Iterator for QueryIter<'w, Q, F> {
+    type Item = Q::Item<'w>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(rows) = &self.sparse_rows {
+            while let Some((idx, row)) = rows.get(self.current).copied() {
+                self.current += 1;
+                if let Some(item) = Self::fetch_row(&self.archetypes[idx], row) {
+                    return Some(item);
+                }
+            }
+            return None;
+        }
+
+        loop {
+            let archetype = self.archetypes.get(self.current)?;
+            if self.row >= archetype.len {
+                self.current += 1;
+                self.row = 0;
+                continue;
+            }
+
+            let row = self.row;
+            self.row += 1;
+            if let Some(item) = Self::fetch_row(archetype, row) {
+                return Some(item);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ecs::storage::StorageType;
+    use crate::engine::ecs::world::{ComponentStorage, DespawnMode};
+
+    #[derive(Debug, PartialEq)]
+    struct Pos(u32);
+    impl Component for Pos {}
+
+    #[derive(Debug, PartialEq)]
+    struct Vel(u32);
+    impl Component for Vel {}
+
+    fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+        items.sort();
+        items
+    }
+
+    #[test]
+    fn reads_rows_across_archetypes() {
+        let mut world = World::new();
+        let pos = world.spawn();
+        world.add_component(pos, Pos(1));
+        let both = world.spawn();
+        world
+            .add_component(both, Pos(2))
+            .add_component(both, Vel(20));
+
+        let rows: Vec<_> = world
+            .query::<(EntityId, &Pos, &Vel)>()
+            .map(|(entity_id, pos, vel)| (entity_id, pos.0, vel.0))
+            .collect();
+        assert_eq!(rows, vec![(both, 2, 20)]);
+
+        let values = world.query::<&Pos>().map(|pos| pos.0).collect();
+        assert_eq!(sorted(values), vec![1, 2]);
+    }
+
+    #[test]
+    fn optional_components() {
+        let mut world = World::new();
+        let pos = world.spawn();
+        world.add_component(pos, Pos(1));
+        let both = world.spawn();
+        world
+            .add_component(both, Pos(2))
+            .add_component(both, Vel(20));
+
+        let rows = world
+            .query::<(&Pos, Option<&Vel>)>()
+            .map(|(pos, vel)| (pos.0, vel.map(|vel| vel.0)))
+            .collect();
+        assert_eq!(sorted(rows), vec![(1, None), (2, Some(20))]);
+    }
+
+    #[test]
+    fn with_and_without_filters() {
+        let mut world = World::new();
+        let pos = world.spawn();
+        world.add_component(pos, Pos(1));
+        let both = world.spawn();
+        world
+            .add_component(both, Pos(2))
+            .add_component(both, Vel(20));
+
+        let with: Vec<_> = world.query_filtered::<EntityId, With<Vel>>().collect();
+        assert_eq!(with, vec![both]);
+        let without: Vec<_> = world
+            .query_filtered::<EntityId, (With<Pos>, Without<Vel>)>()
+            .collect();
+        assert_eq!(without, vec![pos]);
+    }
+
+    #[test]
+    fn changed_filter_on_queried_type() {
+        let mut world = World::new();
+        let first = world.spawn();
+        world.add_component(first, Pos(1));
+        let second = world.spawn();
+        world.add_component(second, Pos(2));
+        world.clear_trackers();
+
+        world.get_mut::<Pos>(&second).unwrap().0 = 3;
+
+        let changed: Vec<_> = world
+            .query_filtered::<&Pos, Changed<Pos>>()
+            .map(|pos| pos.0)
+            .collect();
+        assert_eq!(changed, vec![3]);
+    }
+
+    #[test]
+    fn mutable_access_without_writes_is_not_a_change() {
+        let mut world = World::new();
+        let first = world.spawn();
+        world.add_component(first, Pos(1));
+        let second = world.spawn();
+        world.add_component(second, Pos(2));
+        world.clear_trackers();
+
+        let total: u32 = world.query::<&mut Pos>().map(|pos| pos.0).sum();
+        assert_eq!(total, 3);
+        assert_eq!(world.get_mut::<Pos>(&first).unwrap().0, 1);
+        assert_eq!(world.query_filtered::<EntityId, Changed<Pos>>().count(), 0);
+
+        for mut pos in world.query::<&mut Pos>() {
+            if pos.0 == 2 {
+                pos.0 = 3;
+            }
+        }
+        let changed: Vec<_> = world.query_filtered::<EntityId, Changed<Pos>>().collect();
+        assert_eq!(changed, vec![second]);
+    }
+
+    #[test]
+    fn rows_follow_swap_remove() {
+        let mut world = World::new();
+        let entities: Vec<_> = (0..3)
+            .map(|value| {
+                let entity_id = world.spawn();
+                world.add_component(entity_id, Pos(value));
+                entity_id
+            })
+            .collect();
+
+        world.despawn(entities[0], DespawnMode::Orphan);
+        let rows = world
+            .query::<(EntityId, &Pos)>()
+            .map(|(entity_id, pos)| (entity_id, pos.0))
+            .collect();
+        assert_eq!(sorted(rows), vec![(entities[1], 1), (entities[2], 2)]);
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Sparse(u32);
+    impl Component for Sparse {}
+
+    fn sparse_world() -> World {
+        let mut world = World::new();
+        world
+            .register_component::<Sparse>(StorageType::SparseSet)
+            .unwrap();
+        world
+    }
+
+    #[test]
+    fn reads_table_and_sparse_rows() {
+        let mut world = sparse_world();
+        let pos = world.spawn();
+        world.add_component(pos, Pos(1));
+        let both = world.spawn();
+        world
+            .add_component(both, Pos(2))
+            .add_component(both, Sparse(20));
+        let sparse = world.spawn();
+        world.add_component(sparse, Sparse(30));
+
+        let rows: Vec<_> = world
+            .query::<(EntityId, &Pos, &Sparse)>()
+            .map(|(entity_id, pos, sparse)| (entity_id, pos.0, sparse.0))
+            .collect();
+        assert_eq!(rows, vec![(both, 2, 20)]);
+
+        let rows = world
+            .query::<(&Pos, Option<&Sparse>)>()
+            .map(|(pos, sparse)| (pos.0, sparse.map(|sparse| sparse.0)))
+            .collect();
+        assert_eq!(sorted(rows), vec![(1, None), (2, Some(20))]);
+
+        let with: Vec<_> = world.query_filtered::<EntityId, With<Sparse>>().collect();
+        assert_eq!(sorted(with), sorted(vec![both, sparse]));
+        let without: Vec<_> = world
+            .query_filtered::<EntityId, Without<Sparse>>()
+            .collect();
+        assert_eq!(without, vec![pos]);
+    }
+
+    #[test]
+    fn changed_filter_on_queried_sparse_type() {
+        let mut world = sparse_world();
+        let first = world.spawn();
+        world.add_component(first, Sparse(10));
+        let second = world.spawn();
+        world.add_component(second, Sparse(20));
+        world.clear_trackers();
+
+        world.get_mut::<Sparse>(&first).unwrap().0 = 11;
+
+        let changed: Vec<_> = world
+            .query_filtered::<&Sparse, Changed<Sparse>>()
+            .map(|sparse| sparse.0)
+            .collect();
+        assert_eq!(changed, vec![11]);
+
+        for mut sparse in world.query_filtered::<&mut Sparse, (With<Sparse>, Changed<Sparse>)>() {
+            sparse.0 += 1;
+        }
+        assert_eq!(world.fetch::<Sparse>(&first), Some(&Sparse(12)));
+        assert_eq!(world.fetch::<Sparse>(&second), Some(&Sparse(20)));
+    }
+
+    #[test]
+    fn sparse_rows_follow_swap_remove() {
+        let mut world = sparse_world();
+        let entities: Vec<_> = (0..3)
+            .map(|value| {
+                let entity_id = world.spawn();
+                world.add_component(entity_id, Sparse(value * 10));
+                entity_id
+            })
+            .collect();
+
+        world.remove_component::<Sparse>(&entities[0]);
+        let rows = world
+            .query::<(EntityId, &Sparse)>()
+            .map(|(entity_id, sparse)| (entity_id, sparse.0))
+            .collect();
+        assert_eq!(sorted(rows), vec![(entities[1], 10), (entities[2], 20)]);
+    }
+    #[test]
+    fn sparse_only_queries_resolve_other_terms_per_entity() {
+        #[derive(Debug, PartialEq)]
+        struct Marker;
+        impl Component for Marker {}
+
+        let mut world = sparse_world();
+        world
+            .register_component::<Marker>(StorageType::SparseSet)
+            .unwrap();
+        for value in 0..20 {
+            let entity_id = world.spawn();
+            world.add_component(entity_id, Pos(value));
+        }
+        let mut marked = Vec::new();
+        for value in 0..6 {
+            let entity_id = world.spawn();
+            world.add_component(entity_id, Sparse(value));
+            if value % 2 == 0 {
+                world.add_component(entity_id, Pos(value));
+            }
+            if value < 3 {
+                world.add_component(entity_id, Marker);
+                marked.push((entity_id, value));
+            }
+        }
+
+        let rows = world
+            .query_filtered::<(EntityId, &Sparse), With<Marker>>()
+            .map(|(entity_id, sparse)| (entity_id, sparse.0))
+            .collect();
+        assert_eq!(sorted(rows), marked);
+
+        let rows = world
+            .query_filtered::<(&Sparse, Option<&Pos>), Without<Marker>>()
+            .map(|(sparse, pos)| (sparse.0, pos.map(|pos| pos.0)))
+            .collect();
+        assert_eq!(sorted(rows), vec![(3, None), (4, Some(4)), (5, None)]);
+
+        world.clear_trackers();
+        for mut sparse in world.query_filtered::<&mut Sparse, With<Marker>>() {
+            sparse.0 += 10;
+        }
+        let changed = world
+            .query_filtered::<&Sparse, Changed<Sparse>>()
+            .map(|sparse| sparse.0)
+            .collect();
+        assert_eq!(sorted(changed), vec![10, 11, 12]);
+    }
+}
@@ -0,0 +1,282 @@
+use super::component::{Component, PositionType};
+use super::entity::EntityLabel;
+use super::world::{EntityId, World};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ReflectError {
+    #[error("component {0} is not registered for reflection")]
+    UnknownComponent(String),
+    #[error("component {0} is not attached to entity {1}")]
+    NotAttached(String, EntityLabel),
+    #[error("{component} has no field {field}")]
+    UnknownField {
+        component: &'static str,
+        field: String,
+    },
+    #[error("field {field} expects {expected}, got {found}")]
+    TypeMismatch {
+        field: String,
+        expected: &'static str,
+        found: ReflectValue,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReflectValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl Display for ReflectValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReflectValue::Bool(value) => write!(f, "{value}"),
+            ReflectValue::Int(value) => write!(f, "{value}"),
+            ReflectValue::Float(value) => write!(f, "{value:.2}"),
+            ReflectValue::String(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+// Conversion between a field's concrete type and `ReflectValue`.
+pub trait FieldValue: Sized {
+    const TYPE_NAME: &'static str;
+
+    fn to_value(&self) -> ReflectValue;
+    fn from_value(value: &ReflectValue) -> Option<Self>;
+}
+
+macro_rules! impl_int_field {
+    ($($ty:ty),*) => {
+        $(
+            impl FieldValue for $ty {
+                const TYPE_NAME: &'static str = stringify!($ty);
+
+                fn to_value(&self) -> ReflectValue {
+                    ReflectValue::Int(*self as i64)
+                }
+
+                fn from_value(value: &ReflectValue) -> Option<Self> {
+                    match value {
+                        ReflectValue::Int(value) => (*value).try_into().ok(),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_int_field!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+macro_rules! impl_float_field {
+    ($($ty:ty),*) => {
+        $(
+            impl FieldValue for $ty {
+                const TYPE_NAME: &'static str = stringify!($ty);
+
+                fn to_value(&self) -> ReflectValue {
+                    ReflectValue::Float(*self as f64)
+                }
+
+                fn from_value(value: &ReflectValue) -> Option<Self> {
+                    match value {
+                        ReflectValue::Float(value) => Some(*value as $ty),
+                        ReflectValue::Int(value) => Some(*value as $ty),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_float_field!(f32, f64);
+
+impl FieldValue for bool {
+    const TYPE_NAME: &'static str = "bool";
+
+    fn to_value(&self) -> ReflectValue {
+        ReflectValue::Bool(*self)
+    }
+
+    fn from_value(value: &ReflectValue) -> Option<Self> {
+        match value {
+            ReflectValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FieldValue for String {
+    const TYPE_NAME: &'static str = "String";
+
+    fn to_value(&self) -> ReflectValue {
+        ReflectValue::String(self.clone())
+    }
+
+    fn from_value(value: &ReflectValue) -> Option<Self> {
+        match value {
+            ReflectValue::String(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FieldValue for PositionType {
+    const TYPE_NAME: &'static str = "PositionType";
+
+    fn to_value(&self) -> ReflectValue {
+        let name = match self {
+            PositionType::Abs => "Abs",
+            PositionType::Rel => "Rel",
+        };
+        ReflectValue::String(name.to_string())
+    }
+
+    fn from_value(value: &ReflectValue) -> Option<Self> {
+        match value {
+            ReflectValue::String(value) if value == "Abs" => Some(PositionType::Abs),
+            ReflectValue::String(value) if value == "Rel" => Some(PositionType::Rel),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    pub type_name: &'static str,
+}
+
+pub trait Reflect: Component {
+    fn fields(&self) -> &'static [FieldInfo];
+    fn field(&self, name: &str) -> Option<ReflectValue>;
+    fn set_field(&mut self, name: &str, value: ReflectValue) -> Result<(), ReflectError>;
+}
+
+// Implements `Reflect` by listing the exposed fields, nested and tuple fields
+// are reached with a dotted path:
+//
+// impl_reflect!(Name, "Name" {
+//     "value" => 0: String,
+// });
+#[macro_export]
+macro_rules! impl_reflect {
+    ($ty:ty, $type_name:literal { $($name:literal => $($field:tt).+ : $field_ty:ty),* $(,)? }) => {
+        impl $crate::engine::ecs::reflect::Reflect for $ty {
+            fn fields(&self) -> &'static [$crate::engine::ecs::reflect::FieldInfo] {
+                const FIELDS: &[$crate::engine::ecs::reflect::FieldInfo] = &[$(
+                    $crate::engine::ecs::reflect::FieldInfo {
+                        name: $name,
+                        type_name:
+                            <$field_ty as $crate::engine::ecs::reflect::FieldValue>::TYPE_NAME,
+                    },
+                )*];
+                FIELDS
+            }
+
+            fn field(&self, name: &str) -> Option<$crate::engine::ecs::reflect::ReflectValue> {
+                match name {
+                    $($name => Some($crate::engine::ecs::reflect::FieldValue::to_value(
+                        &self.$($field).+
+                    )),)*
+                    _ => None,
+                }
+            }
+
+            fn set_field(
+                &mut self,
+                name: &str,
+                value: $crate::engine::ecs::reflect::ReflectValue,
+            ) -> Result<(), $crate::engine::ecs::reflect::ReflectError> {
+                match name {
+                    $($name => {
+                        self.$($field).+ =
+                            <$field_ty as $crate::engine::ecs::reflect::FieldValue>::from_value(
+                                &value,
+                            )
+                            .ok_or_else(|| $crate::engine::ecs::reflect::ReflectError::TypeMismatch {
+                                field: name.to_string(),
+                                expected:
+                                    <$field_ty as $crate::engine::ecs::reflect::FieldValue>::TYPE_NAME,
+                                found: value.clone(),
+                            })?;
+                        Ok(())
+                    })*
+                    _ => Err($crate::engine::ecs::reflect::ReflectError::UnknownField {
+                        component: $type_name,
+                        field: name.to_string(),
+                    }),
+                }
+            }
+        }
+    };
+}
+
+pub(crate) struct ReflectComponent {
+    pub name: &'static str,
+    pub get: fn(&World, EntityId) -> Option<&dyn Reflect>,
+    pub get_mut: fn(&mut World, EntityId) -> Option<&mut dyn Reflect>,
+}
+
+fn get_reflect<T: Reflect>(world: &World, entity_id: EntityId) -> Option<&dyn Reflect> {
+    world.fetch::<T>(&entity_id).map(|component| component as &dyn Reflect)
+}
+
+fn get_reflect_mut<T: Reflect>(world: &mut World, entity_id: EntityId) -> Option<&mut dyn Reflect> {
+    world
+        .get_mut::<T>(&entity_id)
+        .map(|component| component.into_inner() as &mut dyn Reflect)
+}
+
+#[derive(Default)]
+pub struct ReflectRegistry {
+    by_type: HashMap<TypeId, ReflectComponent>,
+    by_name: HashMap<&'static str, TypeId>,
+}
+
+impl ReflectRegistry {
+    pub fn register<T: Reflect>(&mut self, name: &'static str) {
+        let type_id = TypeId::of::<T>();
+        if let Some(previous) = self.by_type.get(&type_id) {
+            self.by_name.remove(previous.name);
+        }
+        if let Some(previous) = self.by_name.insert(name, type_id)
+            && previous != type_id
+        {
+            self.by_type.remove(&previous);
+        }
+
+        self.by_type.insert(
+            type_id,
+            ReflectComponent {
+                name,
+                get: get_reflect::<T>,
+                get_mut: get_reflect_mut::<T>,
+            },
+        );
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.by_name.contains_key(name)
+    }
+
+    pub fn name_of(&self, type_id: TypeId) -> Option<&'static str> {
+        Some(self.by_type.get(&type_id)?.name)
+    }
+
+    pub(crate) fn get(&self, type_id: TypeId) -> Option<&ReflectComponent> {
+        self.by_type.get(&type_id)
+    }
+
+    pub(crate) fn get_by_name(&self, name: &str) -> Option<&ReflectComponent> {
+        self.by_type.get(self.by_name.get(name)?)
+    }
+}
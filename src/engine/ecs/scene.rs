@@ -0,0 +1,193 @@
+use super::snapshot::{ComponentInsert, SnapshotError};
+use super::world::{EntityId, World};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SceneError {
+    #[error("scene file {0} could not be read: {1}")]
+    Io(String, std::io::Error),
+    #[error("scene could not be parsed: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("scene names entity {0} more than once")]
+    DuplicateName(String),
+    #[error("entity {entity} refers to unknown parent {parent}")]
+    UnknownParent { entity: String, parent: String },
+    #[error("parent chain of {0} contains a cycle")]
+    Cycle(String),
+    #[error(transparent)]
+    Component(#[from] SnapshotError),
+}
+
+// Human editable description of a set of entities. Component values are
+// keyed by the name they were registered under with
+// `World::register_serializable`, hierarchy is expressed through local names.
+//
+// {
+//     "entities": [
+//         { "name": "board", "components": { "Size": { "x": 10, "y": 20 } } },
+//         { "parent": "board", "components": { "Size": { "x": 1, "y": 1 } } }
+//     ]
+// }
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Scene {
+    #[serde(default)]
+    pub entities: Vec<SceneEntity>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SceneEntity {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub components: BTreeMap<String, Value>,
+}
+
+impl SceneEntity {
+    pub fn named(name: &str) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_parent(mut self, parent: &str) -> Self {
+        self.parent = Some(parent.to_string());
+        self
+    }
+
+    pub fn with_component<T: Serialize>(
+        mut self,
+        name: &str,
+        component: &T,
+    ) -> Result<Self, serde_json::Error> {
+        self.components
+            .insert(name.to_string(), serde_json::to_value(component)?);
+        Ok(self)
+    }
+}
+
+// Entities created by one `Scene::spawn`, in scene order.
+#[derive(Debug, Default)]
+pub struct SceneInstance {
+    pub entities: Vec<EntityId>,
+    names: HashMap<String, EntityId>,
+}
+
+impl SceneInstance {
+    pub fn get(&self, name: &str) -> Option<EntityId> {
+        self.names.get(name).copied()
+    }
+}
+
+impl Scene {
+    pub fn from_json(json: &str) -> Result<Self, SceneError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, SceneError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|err| SceneError::Io(path.display().to_string(), err))?;
+
+        Self::from_json(&json)
+    }
+
+    // Validates the whole scene before spawning anything, so a broken file
+    // never leaves half a level behind.
+    pub fn spawn(&self, world: &mut World) -> Result<SceneInstance, SceneError> {
+        let parents = self.parent_indices()?;
+        let mut inserts: Vec<Vec<ComponentInsert>> = Vec::with_capacity(self.entities.len());
+        for entity in &self.entities {
+            let mut entity_inserts = Vec::with_capacity(entity.components.len());
+            for (name, value) in &entity.components {
+                let serializable = world
+                    .serializable()
+                    .get_by_name(name)
+                    .ok_or_else(|| SnapshotError::UnknownComponent(name.clone()))?;
+                let insert = (serializable.deserialize)(value)
+                    .map_err(|err| SnapshotError::Serde(name.clone(), err))?;
+                entity_inserts.push(insert);
+            }
+            inserts.push(entity_inserts);
+        }
+
+        let mut instance = SceneInstance::default();
+        for (entity, entity_inserts) in self.entities.iter().zip(inserts) {
+            let entity_id = world.spawn();
+            for insert in entity_inserts {
+                insert(world, entity_id);
+            }
+            if let Some(name) = &entity.name {
+                instance.names.insert(name.clone(), entity_id);
+            }
+            instance.entities.push(entity_id);
+        }
+        for (idx, parent) in parents.into_iter().enumerate() {
+            if let Some(parent) = parent {
+                world.set_parent(instance.entities[idx], instance.entities[parent]);
+            }
+        }
+
+        Ok(instance)
+    }
+
+    fn parent_indices(&self) -> Result<Vec<Option<usize>>, SceneError> {
+        let mut names = HashMap::new();
+        for (idx, entity) in self.entities.iter().enumerate() {
+            if let Some(name) = &entity.name
+                && names.insert(name.as_str(), idx).is_some()
+            {
+                return Err(SceneError::DuplicateName(name.clone()));
+            }
+        }
+
+        let parents = self
+            .entities
+            .iter()
+            .enumerate()
+            .map(|(idx, entity)| {
+                let Some(parent) = &entity.parent else {
+                    return Ok(None);
+                };
+                names
+                    .get(parent.as_str())
+                    .copied()
+                    .map(Some)
+                    .ok_or_else(|| SceneError::UnknownParent {
+                        entity: self.display_name(idx),
+                        parent: parent.clone(),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for start in 0..parents.len() {
+            let mut seen = HashSet::new();
+            let mut current = Some(start);
+            while let Some(idx) = current {
+                if !seen.insert(idx) {
+                    return Err(SceneError::Cycle(self.display_name(start)));
+                }
+                current = parents[idx];
+            }
+        }
+
+        Ok(parents)
+    }
+
+    fn display_name(&self, idx: usize) -> String {
+        self.entities[idx]
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("#{idx}"))
+    }
+}
@@ -0,0 +1,238 @@
+use super::storage::Tick;
+use super::world::World;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Stage {
+    Input,
+    PreUpdate,
+    Update,
+    Physics,
+    PostUpdate,
+    RenderPrep,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [
+        Stage::Input,
+        Stage::PreUpdate,
+        Stage::Update,
+        Stage::Physics,
+        Stage::PostUpdate,
+        Stage::RenderPrep,
+    ];
+}
+
+#[derive(Error, Debug)]
+pub enum ScheduleError {
+    #[error("systems {1:?} in stage {0:?} have cyclic ordering constraints")]
+    Cycle(Stage, Vec<&'static str>),
+    #[error("system {0} is already registered")]
+    Duplicate(&'static str),
+}
+
+pub struct System {
+    name: &'static str,
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+    last_run: Tick,
+    run: Box<dyn FnMut(&mut World)>,
+}
+
+impl System {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn before(&mut self, name: &'static str) -> &mut Self {
+        self.before.push(name);
+        self
+    }
+
+    pub fn after(&mut self, name: &'static str) -> &mut Self {
+        self.after.push(name);
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct Schedule {
+    stages: HashMap<Stage, Vec<System>>,
+    // Execution order per stage, rebuilt whenever a system is added.
+    order: HashMap<Stage, Vec<usize>>,
+    dirty: bool,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_system<F>(&mut self, stage: Stage, name: &'static str, system: F) -> &mut System
+    where
+        F: FnMut(&mut World) + 'static,
+    {
+        self.dirty = true;
+        let systems = self.stages.entry(stage).or_default();
+        systems.push(System {
+            name,
+            before: Vec::new(),
+            after: Vec::new(),
+            last_run: 0,
+            run: Box::new(system),
+        });
+
+        systems.last_mut().unwrap()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.stages
+            .values()
+            .flatten()
+            .any(|system| system.name == name)
+    }
+
+    pub fn build(&mut self) -> Result<(), ScheduleError> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut names: Vec<&'static str> = self.stages.values().flatten().map(System::name).collect();
+        names.sort_unstable();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(ScheduleError::Duplicate(pair[0]));
+        }
+
+        self.order.clear();
+        for (&stage, systems) in &self.stages {
+            self.order.insert(stage, Self::sort_stage(stage, systems)?);
+        }
+        self.dirty = false;
+
+        Ok(())
+    }
+
+    pub fn run_stage(&mut self, stage: Stage, world: &mut World) -> Result<(), ScheduleError> {
+        self.build()?;
+
+        let (Some(order), Some(systems)) = (self.order.get(&stage), self.stages.get_mut(&stage))
+        else {
+            return Ok(());
+        };
+        for &idx in order {
+            let system = &mut systems[idx];
+            let previous = world.set_last_change_tick(system.last_run);
+            (system.run)(world);
+            system.last_run = world.increment_change_tick();
+            world.set_last_change_tick(previous);
+        }
+        world.flush_commands();
+
+        Ok(())
+    }
+
+    pub fn run(&mut self, world: &mut World) -> Result<(), ScheduleError> {
+        for stage in Stage::ALL {
+            self.run_stage(stage, world)?;
+        }
+
+        Ok(())
+    }
+
+    // Kahn's algorithm, ties are resolved by registration order. Constraints
+    // naming systems outside of the stage are ignored since stages already run
+    // in a fixed order.
+    fn sort_stage(stage: Stage, systems: &[System]) -> Result<Vec<usize>, ScheduleError> {
+        let index_of = |name: &str| systems.iter().position(|system| system.name == name);
+        let mut edges: Vec<Vec<usize>> = vec![Vec::new(); systems.len()];
+        let mut in_degree = vec![0usize; systems.len()];
+
+        for (idx, system) in systems.iter().enumerate() {
+            for other in system.before.iter().filter_map(|name| index_of(name)) {
+                edges[idx].push(other);
+                in_degree[other] += 1;
+            }
+            for other in system.after.iter().filter_map(|name| index_of(name)) {
+                edges[other].push(idx);
+                in_degree[idx] += 1;
+            }
+        }
+
+        let mut order = Vec::with_capacity(systems.len());
+        let mut ready: Vec<usize> = (0..systems.len()).filter(|&idx| in_degree[idx] == 0).collect();
+        while !ready.is_empty() {
+            let idx = ready.remove(0);
+            order.push(idx);
+            for &next in &edges[idx] {
+                in_degree[next] -= 1;
+                if in_degree[next] == 0 {
+                    let at = ready.partition_point(|&queued| queued < next);
+                    ready.insert(at, next);
+                }
+            }
+        }
+
+        if order.len() != systems.len() {
+            let cyclic = (0..systems.len())
+                .filter(|idx| !order.contains(idx))
+                .map(|idx| systems[idx].name)
+                .collect();
+            return Err(ScheduleError::Cycle(stage, cyclic));
+        }
+
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordering_constraints_pick_the_run_order() {
+        let mut schedule = Schedule::new();
+        let log = |name: &'static str| {
+            move |world: &mut World| {
+                world.resource_or_insert_with(Vec::<&str>::new).push(name);
+            }
+        };
+        schedule.add_system(Stage::Update, "last", log("last"));
+        schedule
+            .add_system(Stage::Update, "first", log("first"))
+            .before("middle");
+        schedule
+            .add_system(Stage::Update, "middle", log("middle"))
+            .before("last");
+        schedule
+            .add_system(Stage::Input, "input", log("input"))
+            .after("last");
+
+        let mut world = World::new();
+        schedule.run(&mut world).unwrap();
+        assert_eq!(
+            world.resource::<Vec<&str>>().unwrap(),
+            &["input", "first", "middle", "last"]
+        );
+    }
+
+    #[test]
+    fn cyclic_constraints_are_an_error() {
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(Stage::Update, "a", |_| {})
+            .before("b")
+            .after("c");
+        schedule.add_system(Stage::Update, "b", |_| {});
+        schedule.add_system(Stage::Update, "c", |_| {}).after("b");
+        schedule.add_system(Stage::Update, "free", |_| {});
+
+        let mut world = World::new();
+        match schedule.run(&mut world) {
+            Err(ScheduleError::Cycle(Stage::Update, systems)) => {
+                assert_eq!(systems, ["a", "b", "c"])
+            }
+            other => panic!("expected a cycle, got {other:?}"),
+        }
+    }
+}
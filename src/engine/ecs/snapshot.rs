@@ -0,0 +1,166 @@
+use super::component::Component;
+use super::entity::EntitiesSnapshot;
+use super::world::{ComponentStorage, EntityId, World};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use log::warn;
+use std::any::{TypeId, type_name};
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("component {component} on entity {entity} is not registered for snapshots")]
+    UnregisteredComponent {
+        component: &'static str,
+        entity: EntityId,
+    },
+    #[error("snapshot contains unknown component {0}")]
+    UnknownComponent(String),
+    #[error("snapshot entity table is inconsistent")]
+    InvalidEntities,
+    #[error("snapshot stores components for entity {0} which is not alive")]
+    DeadEntity(EntityId),
+    #[error("component {0} could not be (de)serialized: {1}")]
+    Serde(String, serde_json::Error),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub entities: EntitiesSnapshot,
+    pub components: Vec<EntitySnapshot>,
+}
+
+// Component values keyed by the name they were registered under.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EntitySnapshot {
+    pub id: EntityId,
+    pub components: BTreeMap<String, Value>,
+}
+
+pub(crate) type ComponentInsert = Box<dyn FnOnce(&mut World, EntityId)>;
+
+pub(crate) struct SerializableComponent {
+    pub name: &'static str,
+    pub serialize: fn(&World, EntityId) -> Option<Result<Value, serde_json::Error>>,
+    // Deserializes up front so a bad value is reported before the world is
+    // touched, the returned closure does the insert.
+    pub deserialize: fn(&Value) -> Result<ComponentInsert, serde_json::Error>,
+}
+
+#[derive(Default)]
+pub struct SnapshotRegistry {
+    by_type: HashMap<TypeId, SerializableComponent>,
+    by_name: HashMap<&'static str, TypeId>,
+}
+
+impl SnapshotRegistry {
+    pub fn register<T>(&mut self, name: &'static str)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        let type_id = TypeId::of::<T>();
+        if let Some(previous) = self.by_type.get(&type_id) {
+            self.by_name.remove(previous.name);
+        }
+        if let Some(previous) = self.by_name.insert(name, type_id)
+            && previous != type_id
+        {
+            warn!("snapshot name {name} now refers to {}", type_name::<T>());
+            self.by_type.remove(&previous);
+        }
+
+        self.by_type.insert(
+            type_id,
+            SerializableComponent {
+                name,
+                serialize: |world, entity_id| {
+                    world.fetch::<T>(&entity_id).map(serde_json::to_value)
+                },
+                deserialize: |value| {
+                    let component = T::deserialize(value)?;
+                    Ok(Box::new(move |world: &mut World, entity_id| {
+                        world.add_component(entity_id, component);
+                    }))
+                },
+            },
+        );
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.by_name.contains_key(name)
+    }
+
+    pub fn name_of(&self, type_id: TypeId) -> Option<&'static str> {
+        Some(self.by_type.get(&type_id)?.name)
+    }
+
+    pub(crate) fn get(&self, type_id: TypeId) -> Option<&SerializableComponent> {
+        self.by_type.get(&type_id)
+    }
+
+    pub(crate) fn get_by_name(&self, name: &str) -> Option<&SerializableComponent> {
+        self.by_type.get(self.by_name.get(name)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ecs::component::{Name, Rotation};
+    use crate::engine::ecs::world::DespawnMode;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn registered_world() -> World {
+        let mut world = World::new();
+        world
+            .register_serializable::<Name>("Name")
+            .register_serializable::<Rotation>("Rotation");
+        world
+    }
+
+    #[test]
+    fn restore_reproduces_the_snapshot() {
+        let mut world = registered_world();
+        let first = world.spawn_with((Name::new("first"), Rotation { x: 90 }));
+        let second = world.spawn_with((Name::new("second"),));
+        world.despawn(second, DespawnMode::Orphan);
+        let snapshot = world.snapshot().unwrap();
+
+        let added = Rc::new(Cell::new(0));
+        let counter = added.clone();
+        world.on_add::<Name>(move |_, _, _| counter.set(counter.get() + 1));
+        let third = world.spawn_with((Name::new("third"),));
+        world.get_mut::<Rotation>(&first).unwrap().x = 180;
+        added.set(0);
+
+        world.restore(&snapshot).unwrap();
+        assert_eq!(
+            serde_json::to_value(world.snapshot().unwrap()).unwrap(),
+            serde_json::to_value(&snapshot).unwrap()
+        );
+        assert_eq!(world.fetch::<Rotation>(&first).unwrap().x, 90);
+        assert_eq!(added.get(), 0);
+        assert_eq!(world.spawn(), third);
+    }
+
+    #[test]
+    fn duplicate_free_slots_are_rejected() {
+        let mut world = registered_world();
+        let entity_id = world.spawn_with((Name::new("kept"),));
+        let dead = world.spawn();
+        world.despawn(dead, DespawnMode::Orphan);
+        let mut snapshot = serde_json::to_value(world.snapshot().unwrap()).unwrap();
+        let free = snapshot["entities"]["free"][0].clone();
+        snapshot["entities"]["free"] = serde_json::json!([free, free]);
+        let snapshot: WorldSnapshot = serde_json::from_value(snapshot).unwrap();
+
+        assert!(matches!(
+            world.restore(&snapshot),
+            Err(SnapshotError::InvalidEntities)
+        ));
+        assert_eq!(world.fetch::<Name>(&entity_id).unwrap().as_str(), "kept");
+    }
+}
@@ -0,0 +1,297 @@
+use super::world::EntityId;
+use std::collections::{HashMap, HashSet};
+
+pub const DEFAULT_CELL_SIZE: usize = 32;
+
+// Absolute collider bounds, min and max corner inclusive. Signed, a collider
+// may reach past the top or left edge of the window.
+pub type Aabb = ((isize, isize), (isize, isize));
+
+pub fn overlaps(a: Aabb, b: Aabb) -> bool {
+    let ((min_x, min_y), (max_x, max_y)) = a;
+    let ((other_min_x, other_min_y), (other_max_x, other_max_y)) = b;
+
+    !(max_x < other_min_x || min_x > other_max_x || max_y < other_min_y || min_y > other_max_y)
+}
+
+// Uniform grid broadphase. Every entity is listed in each cell its bounds
+// touch, so touching edges still end up sharing a cell.
+pub struct SpatialGrid {
+    cell_size: usize,
+    cells: HashMap<(isize, isize), Vec<(EntityId, Aabb)>>,
+    entries: HashMap<EntityId, Aabb>,
+    // Entities to re-index on the next read, `rebuild` re-indexes everything.
+    pending: HashSet<EntityId>,
+    rebuild: bool,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: usize) -> Self {
+        Self {
+            cell_size: cell_size.max(1),
+            cells: HashMap::new(),
+            entries: HashMap::new(),
+            pending: HashSet::new(),
+            rebuild: true,
+        }
+    }
+
+    pub fn cell_size(&self) -> usize {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.rebuild || !self.pending.is_empty()
+    }
+
+    pub fn needs_rebuild(&self) -> bool {
+        self.rebuild
+    }
+
+    // Everything is re-indexed on the next read.
+    pub fn mark_dirty(&mut self) {
+        self.rebuild = true;
+    }
+
+    pub fn mark_entity_dirty(&mut self, entity_id: EntityId) {
+        if !self.rebuild {
+            self.pending.insert(entity_id);
+        }
+    }
+
+    pub(crate) fn take_pending(&mut self) -> HashSet<EntityId> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub(crate) fn mark_clean(&mut self) {
+        self.rebuild = false;
+        self.pending.clear();
+    }
+
+    pub fn aabb(&self, entity_id: EntityId) -> Option<Aabb> {
+        self.entries.get(&entity_id).copied()
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.entries.keys().copied()
+    }
+
+    // Moves the entity only when its bounds changed.
+    pub fn insert(&mut self, entity_id: EntityId, aabb: Aabb) {
+        match self.entries.insert(entity_id, aabb) {
+            Some(previous) if previous == aabb => return,
+            Some(previous) => self.unlink(entity_id, previous),
+            None => {}
+        }
+        for cell in self.cells_of(aabb) {
+            self.cells.entry(cell).or_default().push((entity_id, aabb));
+        }
+    }
+
+    pub fn remove(&mut self, entity_id: EntityId) -> Option<Aabb> {
+        let aabb = self.entries.remove(&entity_id)?;
+        self.unlink(entity_id, aabb);
+
+        Some(aabb)
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(EntityId) -> bool) {
+        let removed: Vec<EntityId> = self.entities().filter(|id| !keep(*id)).collect();
+        for entity_id in removed {
+            self.remove(entity_id);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.entries.clear();
+        self.pending.clear();
+        self.rebuild = true;
+    }
+
+    // Entities whose bounds overlap `aabb`, each reported once.
+    pub fn query(&self, aabb: Aabb) -> Vec<EntityId> {
+        let mut found = Vec::new();
+        for cell in self.cells_of(aabb) {
+            let Some(entities) = self.cells.get(&cell) else {
+                continue;
+            };
+            for (entity_id, other) in entities {
+                if overlaps(aabb, *other) && self.owns_overlap(cell, aabb, *other) {
+                    found.push(*entity_id);
+                }
+            }
+        }
+
+        found
+    }
+
+    // Every overlapping pair once, ordered by id inside the pair.
+    pub fn pairs(&self) -> Vec<(EntityId, EntityId)> {
+        let mut pairs = Vec::new();
+        for (cell, entities) in &self.cells {
+            for (idx, (a, aabb)) in entities.iter().enumerate() {
+                for (b, other) in &entities[idx + 1..] {
+                    if overlaps(*aabb, *other) && self.owns_overlap(*cell, *aabb, *other) {
+                        pairs.push(if a < b { (*a, *b) } else { (*b, *a) });
+                    }
+                }
+            }
+        }
+
+        pairs
+    }
+
+    // Two overlapping bounds share every cell their overlap touches, only the
+    // cell holding the overlap's min corner reports them so no pair repeats.
+    fn owns_overlap(&self, cell: (isize, isize), a: Aabb, b: Aabb) -> bool {
+        let corner = (a.0.0.max(b.0.0), a.0.1.max(b.0.1));
+        cell == self.cell_of(corner)
+    }
+
+    fn unlink(&mut self, entity_id: EntityId, aabb: Aabb) {
+        for cell in self.cells_of(aabb) {
+            if let Some(entities) = self.cells.get_mut(&cell) {
+                entities.retain(|(id, _)| *id != entity_id);
+                if entities.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    // Rounds towards negative infinity, so cells left of the origin do not
+    // fold onto cell 0.
+    fn cell_of(&self, point: (isize, isize)) -> (isize, isize) {
+        let size = self.cell_size as isize;
+        (point.0.div_euclid(size), point.1.div_euclid(size))
+    }
+
+    fn cells_of(&self, aabb: Aabb) -> impl Iterator<Item = (isize, isize)> + use<> {
+        let (min, max) = (self.cell_of(aabb.0), self.cell_of(aabb.1));
+        (min.1..=max.1).flat_map(move |y| (min.0..=max.0).map(move |x| (x, y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SpatialGrid, overlaps};
+    use crate::engine::ecs::component::{Collider, Position, PositionType};
+    use crate::engine::ecs::world::{EntityId, World};
+
+    fn spawn_box(world: &mut World, x: f32, y: f32, size: usize) -> EntityId {
+        world.spawn_with((
+            Position {
+                x,
+                y,
+                position_type: PositionType::Abs,
+            },
+            Collider::aabb((0, 0), (size, size)),
+        ))
+    }
+
+    #[test]
+    fn clean_grid_can_be_read_while_held() {
+        let mut world = World::new();
+        let a = spawn_box(&mut world, 0.0, 0.0, 10);
+        let b = spawn_box(&mut world, 5.0, 5.0, 10);
+
+        let grid = world.spatial_grid();
+        assert_eq!(grid.len(), 2);
+        let collisions = world.get_collision(a).unwrap();
+        assert!(collisions.collision.contains_key(&b));
+        drop(grid);
+    }
+    #[test]
+    fn writes_through_queries_reindex_the_written_entity() {
+        let mut world = World::new();
+        let moved = spawn_box(&mut world, 0.0, 0.0, 10);
+        let still = spawn_box(&mut world, 40.0, 0.0, 10);
+        assert!(world.spatial_grid().pairs().is_empty());
+
+        for position in world.query::<&mut Position>() {
+            assert!(position.x >= 0.0);
+        }
+
+        for (entity_id, mut position) in world.query::<(EntityId, &mut Position)>() {
+            if entity_id == moved {
+                position.x = 35.0;
+            }
+        }
+        let grid = world.spatial_grid();
+        assert_eq!(grid.aabb(moved), Some(((35, 0), (45, 10))));
+        assert_eq!(grid.pairs().len(), 1);
+        assert_eq!(grid.aabb(still), Some(((40, 0), (50, 10))));
+    }
+
+    #[test]
+    fn bounds_left_of_the_origin_keep_their_cells() {
+        let mut world = World::new();
+        let left = spawn_box(&mut world, -20.0, -5.0, 10);
+        spawn_box(&mut world, 0.0, 0.0, 10);
+
+        let grid = world.spatial_grid();
+        assert_eq!(grid.aabb(left), Some(((-20, -5), (-10, 5))));
+        assert_eq!(grid.query(((-12, 0), (-11, 1))), vec![left]);
+        assert!(grid.pairs().is_empty());
+    }
+    #[test]
+    fn pairs_and_queries_match_brute_force() {
+        let mut grid = SpatialGrid::new(16);
+        // Fixed pseudo random boxes, some spanning several cells or reaching
+        // past the origin.
+        let mut seed = 7u32;
+        let mut next = |range: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            ((seed >> 16) % range) as isize
+        };
+        let mut world = World::new();
+        let mut boxes = Vec::new();
+        for _ in 0..200 {
+            let (x, y) = (next(200) - 40, next(200) - 40);
+            let aabb = ((x, y), (x + next(40), y + next(40)));
+            let entity_id = world.spawn();
+            grid.insert(entity_id, aabb);
+            boxes.push((entity_id, aabb));
+        }
+
+        let mut expected = Vec::new();
+        for (idx, (a, aabb)) in boxes.iter().enumerate() {
+            for (b, other) in &boxes[idx + 1..] {
+                if overlaps(*aabb, *other) {
+                    expected.push(if a < b { (*a, *b) } else { (*b, *a) });
+                }
+            }
+        }
+        let mut pairs = grid.pairs();
+        pairs.sort();
+        expected.sort();
+        assert!(!expected.is_empty());
+        assert_eq!(pairs, expected);
+
+        let probe = ((-10, 30), (50, 45));
+        let mut found = grid.query(probe);
+        found.sort();
+        let mut expected: Vec<EntityId> = boxes
+            .iter()
+            .filter(|(_, aabb)| overlaps(probe, *aabb))
+            .map(|(entity_id, _)| *entity_id)
+            .collect();
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+}
@@ -0,0 +1,296 @@
+use super::component::Component;
+use super::world::EntityId;
+use std::any::{Any, type_name};
+
+pub type Tick = u64;
+
+#[derive(Copy, Clone, Debug)]
+pub struct ComponentTicks {
+    pub added: Tick,
+    pub changed: Tick,
+}
+
+impl ComponentTicks {
+    pub fn is_added(&self, last_tick: Tick) -> bool {
+        self.added > last_tick
+    }
+
+    pub fn is_changed(&self, last_tick: Tick) -> bool {
+        self.changed > last_tick
+    }
+}
+
+// Table components live in archetype columns and are fastest to iterate,
+// sparse set components can be added and removed without moving the entity
+// between archetypes.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum StorageType {
+    #[default]
+    Table,
+    SparseSet,
+}
+
+pub(crate) trait AnySparseSet {
+    fn type_name(&self) -> &'static str;
+    fn contains(&self, entity_id: EntityId) -> bool;
+    fn clear(&mut self);
+    fn remove_drop(&mut self, entity_id: EntityId) -> bool;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+// `sparse` is indexed by entity index and points into the packed `dense`,
+// `ticks` and `entities` vectors.
+pub struct SparseSet<T: Component> {
+    sparse: Vec<Option<usize>>,
+    dense: Vec<T>,
+    ticks: Vec<ComponentTicks>,
+    entities: Vec<EntityId>,
+}
+
+impl<T: Component> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self {
+            sparse: Vec::new(),
+            dense: Vec::new(),
+            ticks: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl<T: Component> SparseSet<T> {
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    pub fn contains(&self, entity_id: EntityId) -> bool {
+        self.dense_index(entity_id).is_some()
+    }
+
+    pub fn insert(&mut self, entity_id: EntityId, component: T, tick: Tick) -> Option<T> {
+        if let Some(idx) = self.dense_index(entity_id) {
+            self.ticks[idx].changed = tick;
+            return Some(std::mem::replace(&mut self.dense[idx], component));
+        }
+
+        let slot = entity_id.index() as usize;
+        if self.sparse.len() <= slot {
+            self.sparse.resize(slot + 1, None);
+        }
+        self.sparse[slot] = Some(self.dense.len());
+        self.dense.push(component);
+        self.ticks.push(ComponentTicks {
+            added: tick,
+            changed: tick,
+        });
+        self.entities.push(entity_id);
+
+        None
+    }
+
+    pub fn remove(&mut self, entity_id: EntityId) -> Option<T> {
+        let idx = self.dense_index(entity_id)?;
+        self.sparse[entity_id.index() as usize] = None;
+        self.ticks.swap_remove(idx);
+        self.entities.swap_remove(idx);
+        if let Some(swapped) = self.entities.get(idx) {
+            self.sparse[swapped.index() as usize] = Some(idx);
+        }
+
+        Some(self.dense.swap_remove(idx))
+    }
+
+    pub fn get(&self, entity_id: EntityId) -> Option<&T> {
+        self.dense.get(self.dense_index(entity_id)?)
+    }
+
+    pub fn get_mut(&mut self, entity_id: EntityId) -> Option<(&mut T, &mut ComponentTicks)> {
+        let idx = self.dense_index(entity_id)?;
+        Some((&mut self.dense[idx], &mut self.ticks[idx]))
+    }
+
+    pub fn component_ticks(&self, entity_id: EntityId) -> Option<ComponentTicks> {
+        self.ticks.get(self.dense_index(entity_id)?).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> {
+        self.entities.iter().copied().zip(self.dense.iter())
+    }
+
+    pub fn entities(&self) -> &[EntityId] {
+        &self.entities
+    }
+
+    pub fn ticks(&self) -> &[ComponentTicks] {
+        &self.ticks
+    }
+
+    pub(crate) fn dense_index(&self, entity_id: EntityId) -> Option<usize> {
+        let idx = (*self.sparse.get(entity_id.index() as usize)?)?;
+        (self.entities[idx] == entity_id).then_some(idx)
+    }
+
+    pub(crate) fn data_ptr(&mut self) -> *mut T {
+        self.dense.as_mut_ptr()
+    }
+
+    pub(crate) fn ticks_ptr(&mut self) -> *mut ComponentTicks {
+        self.ticks.as_mut_ptr()
+    }
+}
+
+impl<T: Component> AnySparseSet for SparseSet<T> {
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+
+    fn contains(&self, entity_id: EntityId) -> bool {
+        self.dense_index(entity_id).is_some()
+    }
+
+    fn clear(&mut self) {
+        self.sparse.clear();
+        self.dense.clear();
+        self.ticks.clear();
+        self.entities.clear();
+    }
+
+    fn remove_drop(&mut self, entity_id: EntityId) -> bool {
+        self.remove(entity_id).is_some()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ecs::ComponentError;
+    use crate::engine::ecs::entity::Entities;
+    use crate::engine::ecs::world::{ComponentStorage, DespawnMode, World};
+
+    #[derive(Debug, PartialEq)]
+    struct Value(u32);
+    impl Component for Value {}
+
+    #[test]
+    fn insert_replaces_and_bumps_changed_tick() {
+        let mut entities = Entities::default();
+        let entity_id = entities.alloc();
+        let mut set = SparseSet::default();
+
+        assert_eq!(set.insert(entity_id, Value(1), 1), None);
+        assert_eq!(set.insert(entity_id, Value(2), 3), Some(Value(1)));
+        assert_eq!(set.get(entity_id), Some(&Value(2)));
+        assert_eq!(set.len(), 1);
+
+        let ticks = set.component_ticks(entity_id).unwrap();
+        assert_eq!((ticks.added, ticks.changed), (1, 3));
+    }
+
+    #[test]
+    fn remove_swaps_last_into_hole() {
+        let mut entities = Entities::default();
+        let ids: Vec<_> = (0..3).map(|_| entities.alloc()).collect();
+        let mut set = SparseSet::default();
+        for (value, entity_id) in ids.iter().enumerate() {
+            set.insert(*entity_id, Value(value as u32), value as Tick);
+        }
+
+        assert_eq!(set.remove(ids[0]), Some(Value(0)));
+        assert_eq!(set.remove(ids[0]), None);
+        assert_eq!(set.dense_index(ids[2]), Some(0));
+        assert_eq!(set.entities(), &[ids[2], ids[1]]);
+        assert_eq!(set.get(ids[1]), Some(&Value(1)));
+        assert_eq!(set.get(ids[2]), Some(&Value(2)));
+        assert_eq!(set.component_ticks(ids[2]).unwrap().added, 2);
+
+        // Removing the last row swaps nothing.
+        assert_eq!(set.remove(ids[1]), Some(Value(1)));
+        assert_eq!(set.entities(), &[ids[2]]);
+        assert_eq!(set.get(ids[2]), Some(&Value(2)));
+    }
+
+    #[test]
+    fn stale_generation_misses_reused_index() {
+        let mut entities = Entities::default();
+        let stale = entities.alloc();
+        let mut set = SparseSet::default();
+        set.insert(stale, Value(1), 0);
+        set.remove(stale);
+        entities.free(stale);
+
+        let reused = entities.alloc();
+        assert_eq!(reused.index(), stale.index());
+        assert!(!set.contains(reused));
+
+        set.insert(reused, Value(2), 1);
+        assert!(!set.contains(stale));
+        assert_eq!(set.get(stale), None);
+        assert!(set.get_mut(stale).is_none());
+        assert_eq!(set.remove(stale), None);
+        assert_eq!(set.get(reused), Some(&Value(2)));
+    }
+
+    #[test]
+    fn queries_skip_stale_sparse_rows() {
+        let mut world = World::new();
+        world
+            .register_component::<Value>(StorageType::SparseSet)
+            .unwrap();
+        let stale = world.spawn();
+        world.add_component(stale, Value(1));
+        world.despawn(stale, DespawnMode::Orphan);
+
+        let reused = world.spawn();
+        assert_eq!(reused.index(), stale.index());
+        assert_eq!(world.query::<&Value>().count(), 0);
+        assert_eq!(world.fetch::<Value>(&stale), None);
+
+        world.add_component(reused, Value(2));
+        let rows: Vec<_> = world
+            .query::<(EntityId, &Value)>()
+            .map(|(entity_id, value)| (entity_id, value.0))
+            .collect();
+        assert_eq!(rows, vec![(reused, 2)]);
+    }
+
+    #[test]
+    fn storage_cannot_switch_while_in_use() {
+        let mut world = World::new();
+        world
+            .register_component::<Value>(StorageType::SparseSet)
+            .unwrap();
+        let entity_id = world.spawn();
+        world.add_component(entity_id, Value(1));
+
+        let err = world.register_component::<Value>(StorageType::Table).err();
+        assert!(matches!(err, Some(ComponentError::StorageInUse(_))));
+        assert_eq!(world.storage_type::<Value>(), StorageType::SparseSet);
+        assert_eq!(world.fetch::<Value>(&entity_id), Some(&Value(1)));
+
+        world.remove_component::<Value>(&entity_id);
+        world
+            .register_component::<Value>(StorageType::Table)
+            .unwrap();
+        world.add_component(entity_id, Value(2));
+
+        let err = world
+            .register_component::<Value>(StorageType::SparseSet)
+            .err();
+        assert!(matches!(err, Some(ComponentError::StorageInUse(_))));
+        assert_eq!(world.storage_type::<Value>(), StorageType::Table);
+        assert_eq!(world.query::<&Value>().next(), Some(&Value(2)));
+    }
+}
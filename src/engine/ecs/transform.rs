@@ -0,0 +1,226 @@
+use super::component::{Children, Component, Parent};
+use super::query::Without;
+use super::world::{EntityId, World};
+use crate::engine::ecs::ComponentError;
+use glam::{Affine2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// Local transform, relative to the parent's `GlobalTransform` when the entity
+// has a `Parent`. Rotation is counter-clockwise in radians. Kept apart from
+// `Position`, which collision and rendering read, see `World::world_placement`.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Transform {
+    pub translation: Vec2,
+    pub rotation: f32,
+    pub scale: Vec2,
+}
+impl Component for Transform {}
+crate::impl_reflect!(Transform, "Transform" {
+    "translation_x" => translation.x: f32,
+    "translation_y" => translation.y: f32,
+    "rotation" => rotation: f32,
+    "scale_x" => scale.x: f32,
+    "scale_y" => scale.y: f32,
+});
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vec2::ZERO,
+        rotation: 0.0,
+        scale: Vec2::ONE,
+    };
+
+    pub fn from_xy(x: f32, y: f32) -> Self {
+        Self::from_translation(Vec2::new(x, y))
+    }
+
+    pub fn from_translation(translation: Vec2) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec2) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn compute_affine(&self) -> Affine2 {
+        Affine2::from_scale_angle_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+// World space transform, written by `propagate_transforms`.
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct GlobalTransform(pub Affine2);
+impl Component for GlobalTransform {}
+
+impl GlobalTransform {
+    pub fn affine(&self) -> Affine2 {
+        self.0
+    }
+
+    pub fn translation(&self) -> Vec2 {
+        self.0.translation
+    }
+
+    pub fn transform_point(&self, point: Vec2) -> Vec2 {
+        self.0.transform_point2(point)
+    }
+}
+
+// Walks the hierarchy top-down from every root and recomputes each
+// `GlobalTransform` once, writing it only when it moved. Entities without a
+// `Transform` pass their parent's transform through to their children.
+// Entities reached twice are reported as a cycle, entities never reached by
+// their ancestry loop or a dangling parent.
+pub fn propagate_transforms(world: &mut World) -> Result<(), ComponentError> {
+    let mut pending: Vec<(EntityId, Affine2)> = world
+        .query_filtered::<EntityId, Without<Parent>>()
+        .map(|entity_id| (entity_id, Affine2::IDENTITY))
+        .collect();
+    let mut visited = HashSet::new();
+    let mut cycle = None;
+
+    while let Some((entity_id, parent)) = pending.pop() {
+        if !visited.insert(entity_id) {
+            cycle.get_or_insert(entity_id);
+            continue;
+        }
+
+        let global = match world.fetch::<Transform>(&entity_id) {
+            Some(transform) => {
+                let global = parent * transform.compute_affine();
+                match world.get_mut::<GlobalTransform>(&entity_id) {
+                    Some(mut current) if current.0 != global => current.0 = global,
+                    Some(_) => {}
+                    None => {
+                        world.try_add_component(entity_id, GlobalTransform(global))?;
+                    }
+                }
+                global
+            }
+            None => parent,
+        };
+        if let Some(children) = world.fetch::<Children>(&entity_id) {
+            pending.extend(children.entities().iter().map(|child| (*child, global)));
+        }
+    }
+
+    if let Some(entity_id) = cycle {
+        return Err(ComponentError::HierarchyCycle(world.label(entity_id)));
+    }
+    match world
+        .query::<EntityId>()
+        .find(|entity_id| !visited.contains(entity_id))
+    {
+        Some(entity_id) => Err(unreached(world, entity_id)),
+        None => Ok(()),
+    }
+}
+
+// Follows the parents of an entity no root reaches, up to the first link that
+// is dead or missing from its parent's `Children`, or around a loop.
+fn unreached(world: &World, entity_id: EntityId) -> ComponentError {
+    let mut seen = HashSet::new();
+    let mut current = entity_id;
+    while seen.insert(current) {
+        let linked = world.fetch::<Parent>(&current).filter(|parent| {
+            world
+                .fetch::<Children>(&parent.entity)
+                .is_some_and(|children| children.entities().contains(&current))
+        });
+        match linked {
+            Some(parent) => current = parent.entity,
+            None => return ComponentError::DanglingParent(world.label(current)),
+        }
+    }
+
+    ComponentError::HierarchyCycle(world.label(entity_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ecs::query::Changed;
+    use crate::engine::ecs::world::{ComponentStorage, DespawnMode};
+
+    #[test]
+    fn unchanged_transforms_are_not_rewritten() {
+        let mut world = World::new();
+        let parent = world.spawn_with((Transform::from_xy(10.0, 0.0),));
+        let child = world.spawn_with((Transform::from_xy(0.0, 5.0),));
+        world.set_parent(child, parent);
+        propagate_transforms(&mut world).unwrap();
+        assert_eq!(
+            world
+                .fetch::<GlobalTransform>(&child)
+                .unwrap()
+                .translation(),
+            Vec2::new(10.0, 5.0)
+        );
+
+        world.clear_trackers();
+        propagate_transforms(&mut world).unwrap();
+        assert_eq!(
+            world
+                .query_filtered::<EntityId, Changed<GlobalTransform>>()
+                .count(),
+            0
+        );
+
+        world.get_mut::<Transform>(&parent).unwrap().translation.x = 20.0;
+        propagate_transforms(&mut world).unwrap();
+        let mut changed: Vec<_> = world
+            .query_filtered::<EntityId, Changed<GlobalTransform>>()
+            .collect();
+        changed.sort();
+        assert_eq!(changed, vec![parent, child]);
+    }
+
+    #[test]
+    fn dead_parent_is_not_a_cycle() {
+        let mut world = World::new();
+        let parent = world.spawn_with((Transform::IDENTITY,));
+        let child = world.spawn_with((Transform::IDENTITY,));
+        world.set_parent(child, parent);
+        world.despawn(parent, DespawnMode::Orphan);
+        world.add_component(child, Parent { entity: parent });
+
+        assert!(matches!(
+            propagate_transforms(&mut world),
+            Err(ComponentError::DanglingParent(label)) if label.id == child
+        ));
+    }
+
+    #[test]
+    fn looping_parents_are_a_cycle() {
+        let mut world = World::new();
+        let first = world.spawn_with((Transform::IDENTITY,));
+        let second = world.spawn_with((Transform::IDENTITY,));
+        world.set_parent(first, second);
+        let mut children = Children::default();
+        children.add_entity(second);
+        world
+            .add_component(second, Parent { entity: first })
+            .add_component(first, children);
+
+        assert!(matches!(
+            propagate_transforms(&mut world),
+            Err(ComponentError::HierarchyCycle(_))
+        ));
+    }
+}
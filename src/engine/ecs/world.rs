@@ -1,10 +1,32 @@
+use super::archetype::{
+    AnyColumn, Archetype, ArchetypeId, Archetypes, Column, EMPTY_ARCHETYPE, EntityLocation,
+};
+use super::builder::EntityBuilder;
+use super::bundle::Bundle;
+use super::command::Commands;
+use super::event::Events;
+use super::hooks::{Hooks, Lifecycle};
 use super::component::*;
+use super::entity::{Entities, EntityLabel};
+use super::reflect::{Reflect, ReflectError, ReflectRegistry};
+use super::prefab::{Prefab, PrefabError, PrefabInstance, PrefabOverrides, Prefabs};
+use super::collision::{RoundedConvex, intersect};
+use super::spatial::{Aabb, SpatialGrid};
+use super::snapshot::{EntitySnapshot, SnapshotError, SnapshotRegistry, WorldSnapshot};
+use super::transform::{GlobalTransform, Transform};
+use super::query::{Mut, QueryData, QueryFilter, QueryIter};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use super::storage::{AnySparseSet, ComponentTicks, SparseSet, StorageType, Tick};
 use crate::engine::ecs::ComponentError;
 use crate::engine::render::Position as RenderPosition;
+use crate::engine::resource::Sprite;
+use std::any::{Any, TypeId, type_name};
+use std::cell::{Ref, RefCell};
+use log::warn;
 use std::collections::{HashMap, HashSet};
-use std::default::Default;
-// TODO: EntityBuilder
-pub type EntityId = u32;
+use glam::Vec2;
+pub use super::entity::EntityId;
 
 pub trait Entity {
     fn get_id(&self) -> EntityId;
@@ -13,166 +35,1234 @@ pub trait Entity {
 pub trait ComponentStorage<T: Component> {
     fn add_component(&mut self, entity_id: EntityId, component: T) -> &mut Self;
     fn fetch(&self, entity_id: &EntityId) -> Option<&T>;
-    fn get_mut(&mut self, entity_id: &EntityId) -> Option<&mut T>;
+    fn get_mut(&mut self, entity_id: &EntityId) -> Option<Mut<'_, T>>;
+}
+
+impl<T: Component> ComponentStorage<T> for World {
+    fn add_component(&mut self, entity_id: EntityId, component: T) -> &mut Self {
+        if let Err(err) = self.try_add_component(entity_id, component) {
+            warn!("{err}");
+        }
+
+        self
+    }
+
+    fn fetch(&self, entity_id: &EntityId) -> Option<&T> {
+        if let Some(set) = self.sparse_set::<T>() {
+            return set.get(*entity_id);
+        }
+        let location = self.entities.location(*entity_id)?;
+        self.archetypes
+            .get(location.archetype)?
+            .column::<T>()?
+            .get(location.row)
+    }
+
+    fn get_mut(&mut self, entity_id: &EntityId) -> Option<Mut<'_, T>> {
+        let type_id = TypeId::of::<T>();
+        let spatial = Self::affects_spatial(type_id).then_some((&*self.spatial, *entity_id));
+        let (value, ticks) = match self.sparse_sets.get_mut(&type_id) {
+            Some(set) => set
+                .as_any_mut()
+                .downcast_mut::<SparseSet<T>>()?
+                .get_mut(*entity_id)?,
+            None => {
+                let location = self.entities.location(*entity_id)?;
+                self.archetypes
+                    .get_mut(location.archetype)?
+                    .column_mut::<T>()?
+                    .get_mut(location.row)?
+            }
+        };
+
+        Some(Mut::new(value, ticks, self.change_tick, spatial))
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DespawnMode {
+    Orphan,
+    Recursive,
 }
 
 pub struct World {
-    pub(crate) positions: HashMap<EntityId, Position>,
-    pub(crate) colliders: HashMap<EntityId, Collider>,
-    pub(crate) rotation: HashMap<EntityId, Rotation>,
-    pub(crate) children: HashMap<EntityId, Children>,
-    pub(crate) sizes: HashMap<EntityId, Size>,
-    pub(crate) parent: HashMap<EntityId, Parent>,
-    next_entity_id: EntityId,
+    archetypes: Archetypes,
+    sparse_sets: HashMap<TypeId, Box<dyn AnySparseSet>>,
+    removed: HashMap<TypeId, Vec<(EntityId, Tick)>>,
+    resources: HashMap<TypeId, Box<dyn Any>>,
+    entities: Entities,
+    serializable: SnapshotRegistry,
+    reflectable: ReflectRegistry,
+    deferred: Commands,
+    hooks: Hooks,
+    // Commands queued by hooks, applied when the outermost structural
+    // operation returns.
+    hook_commands: Commands,
+    hook_depth: usize,
+    // Boxed so `Mut` guards can keep pointing at it while queries hold the
+    // rest of the world mutably.
+    spatial: Box<RefCell<SpatialGrid>>,
+    event_updaters: HashMap<TypeId, fn(&mut World)>,
+    change_tick: Tick,
+    last_change_tick: Tick,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl World {
     pub fn new() -> Self {
-        Self {
-            positions: Default::default(),
-            colliders: Default::default(),
-            rotation: Default::default(),
-            children: Default::default(),
-            sizes: Default::default(),
-            parent: Default::default(),
-            next_entity_id: 1,
+        let mut world = Self {
+            archetypes: Archetypes::default(),
+            sparse_sets: HashMap::new(),
+            removed: HashMap::new(),
+            resources: HashMap::new(),
+            entities: Entities::default(),
+            serializable: SnapshotRegistry::default(),
+            reflectable: ReflectRegistry::default(),
+            deferred: Commands::default(),
+            hooks: Hooks::default(),
+            hook_commands: Commands::default(),
+            hook_depth: 0,
+            spatial: Box::new(RefCell::new(SpatialGrid::default())),
+            event_updaters: HashMap::new(),
+            change_tick: 1,
+            last_change_tick: 0,
+        };
+        world
+            .register_serializable::<Position>("Position")
+            .register_serializable::<Rotation>("Rotation")
+            .register_serializable::<Size>("Size")
+            .register_serializable::<Collider>("Collider")
+            .register_serializable::<Parent>("Parent")
+            .register_serializable::<Children>("Children")
+            .register_serializable::<Transform>("Transform")
+            .register_serializable::<GlobalTransform>("GlobalTransform")
+            .register_serializable::<Sprite>("Sprite")
+            .register_serializable::<Name>("Name")
+            .register_serializable::<CollisionIgnore>("CollisionIgnore")
+            .register_reflect::<Position>("Position")
+            .register_reflect::<Rotation>("Rotation")
+            .register_reflect::<Size>("Size")
+            .register_reflect::<Collider>("Collider")
+            .register_reflect::<Transform>("Transform")
+            .register_reflect::<Name>("Name");
+
+        world
+    }
+
+    pub fn change_tick(&self) -> Tick {
+        self.change_tick
+    }
+
+    // Returns the tick that was current before the increment, changes made
+    // from now on compare newer than it.
+    pub fn increment_change_tick(&mut self) -> Tick {
+        let tick = self.change_tick;
+        self.change_tick += 1;
+
+        tick
+    }
+
+    // `Changed`, `Added` and `removed` report everything newer than this tick.
+    // The scheduler swaps in each system's last run while it executes.
+    pub fn last_change_tick(&self) -> Tick {
+        self.last_change_tick
+    }
+
+    pub fn set_last_change_tick(&mut self, tick: Tick) -> Tick {
+        std::mem::replace(&mut self.last_change_tick, tick)
+    }
+
+    pub fn clear_trackers(&mut self) {
+        let previous = self.last_change_tick;
+        for removed in self.removed.values_mut() {
+            removed.retain(|(_, tick)| *tick > previous);
         }
+        self.last_change_tick = self.increment_change_tick();
     }
 
-    pub fn spawn(&mut self) -> EntityId {
-        let entity_id = self.next_entity_id;
-        self.next_entity_id += 1;
+    pub fn component_ticks<T: Component>(&self, entity_id: &EntityId) -> Option<ComponentTicks> {
+        if let Some(set) = self.sparse_set::<T>() {
+            return set.component_ticks(*entity_id);
+        }
+        let location = self.entities.location(*entity_id)?;
+        self.archetypes
+            .get(location.archetype)?
+            .column_ticks(TypeId::of::<T>(), location.row)
+    }
 
-        entity_id
+    pub fn removed<T: Component>(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.removed_since::<T>(self.last_change_tick)
+    }
+
+    pub fn removed_since<T: Component>(&self, tick: Tick) -> impl Iterator<Item = EntityId> + '_ {
+        self.removed
+            .get(&TypeId::of::<T>())
+            .into_iter()
+            .flatten()
+            .filter(move |(_, removed_tick)| *removed_tick > tick)
+            .map(|(entity_id, _)| *entity_id)
+    }
+
+    pub fn changed_since<T: Component>(&self, tick: Tick) -> bool {
+        self.removed_since::<T>(tick).next().is_some()
+            || self
+                .sparse_set::<T>()
+                .is_some_and(|set| set.ticks().iter().any(|ticks| ticks.is_changed(tick)))
+            || self
+                .archetypes
+                .iter()
+                .filter_map(|archetype| archetype.column::<T>())
+                .any(|column| column.ticks().iter().any(|ticks| ticks.is_changed(tick)))
+    }
+
+    pub fn add_event<E: 'static>(&mut self) -> &mut Events<E> {
+        self.event_updaters
+            .entry(TypeId::of::<Events<E>>())
+            .or_insert(|world| {
+                if let Some(events) = world.resource_mut::<Events<E>>() {
+                    events.update();
+                }
+            });
+
+        self.resource_or_insert_with(Events::<E>::default)
+    }
+
+    pub fn send_event<E: 'static>(&mut self, event: E) {
+        self.add_event::<E>().send(event);
+    }
+
+    pub fn update_events(&mut self) {
+        let updaters: Vec<fn(&mut World)> = self.event_updaters.values().copied().collect();
+        for update in updaters {
+            update(self);
+        }
+    }
+
+    pub fn defer(&mut self, mut commands: Commands) {
+        self.deferred.append(&mut commands);
+    }
+
+    pub fn flush_commands(&mut self) {
+        while !self.deferred.is_empty() {
+            let mut commands = std::mem::take(&mut self.deferred);
+            commands.apply(self);
+        }
+    }
+
+    // Several hooks may observe the same component and lifecycle event, they
+    // run in registration order.
+    pub fn observe<T: Component>(
+        &mut self,
+        lifecycle: Lifecycle,
+        hook: impl Fn(&World, EntityId, &mut Commands) + 'static,
+    ) -> &mut Self {
+        self.hooks
+            .register(TypeId::of::<T>(), lifecycle, Box::new(hook));
+
+        self
+    }
+
+    pub fn on_add<T: Component>(
+        &mut self,
+        hook: impl Fn(&World, EntityId, &mut Commands) + 'static,
+    ) -> &mut Self {
+        self.observe::<T>(Lifecycle::Added, hook)
+    }
+
+    pub fn on_replace<T: Component>(
+        &mut self,
+        hook: impl Fn(&World, EntityId, &mut Commands) + 'static,
+    ) -> &mut Self {
+        self.observe::<T>(Lifecycle::Replaced, hook)
+    }
+
+    pub fn on_remove<T: Component>(
+        &mut self,
+        hook: impl Fn(&World, EntityId, &mut Commands) + 'static,
+    ) -> &mut Self {
+        self.observe::<T>(Lifecycle::Removed, hook)
+    }
+
+    fn run_hooks(&mut self, type_ids: &[TypeId], lifecycle: Lifecycle, entity_id: EntityId) {
+        if self.hooks.is_empty() {
+            return;
+        }
+
+        let mut commands = Commands::new();
+        for type_id in type_ids {
+            for hook in self.hooks.get(*type_id, lifecycle) {
+                hook(self, entity_id, &mut commands);
+            }
+        }
+        self.hook_commands.append(&mut commands);
+    }
+
+    // Runs a structural operation. Hook commands are only applied once the
+    // outermost operation is done, so a hook that undoes its own trigger, like
+    // despawning on `Removed`, queues work instead of recursing.
+    fn with_hooks<R>(&mut self, operation: impl FnOnce(&mut World) -> R) -> R {
+        self.hook_depth += 1;
+        let result = operation(self);
+        self.hook_depth -= 1;
+        if self.hook_depth == 0 {
+            self.apply_hook_commands();
+        }
+
+        result
+    }
+
+    fn apply_hook_commands(&mut self) {
+        // Operations run by the commands stay nested and only queue.
+        self.hook_depth += 1;
+        while !self.hook_commands.is_empty() {
+            let mut commands = std::mem::take(&mut self.hook_commands);
+            commands.apply(self);
+        }
+        self.hook_depth -= 1;
+    }
+
+    pub fn insert_resource<R: 'static>(&mut self, resource: R) -> Option<R> {
+        self.resources
+            .insert(TypeId::of::<R>(), Box::new(resource))
+            .and_then(|old| old.downcast::<R>().ok())
+            .map(|old| *old)
+    }
+
+    pub fn remove_resource<R: 'static>(&mut self) -> Option<R> {
+        self.resources
+            .remove(&TypeId::of::<R>())
+            .and_then(|old| old.downcast::<R>().ok())
+            .map(|old| *old)
+    }
+
+    pub fn has_resource<R: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    pub fn resource<R: 'static>(&self) -> Option<&R> {
+        self.resources.get(&TypeId::of::<R>())?.downcast_ref::<R>()
+    }
+
+    pub fn resource_mut<R: 'static>(&mut self) -> Option<&mut R> {
+        self.resources.get_mut(&TypeId::of::<R>())?.downcast_mut::<R>()
+    }
+
+    pub fn resource_or_insert_with<R: 'static>(&mut self, init: impl FnOnce() -> R) -> &mut R {
+        self.resources
+            .entry(TypeId::of::<R>())
+            .or_insert_with(|| Box::new(init()))
+            .downcast_mut::<R>()
+            .expect("resource registered under a foreign TypeId")
     }
 
-    pub fn fetch<T: Component>(&self, id: &EntityId) -> Option<&T>
+    // Components default to table storage, a type can only switch while no
+    // entity holds it.
+    pub fn register_component<T: Component>(
+        &mut self,
+        storage: StorageType,
+    ) -> Result<&mut Self, ComponentError> {
+        if self.storage_type::<T>() == storage {
+            return Ok(self);
+        }
+
+        let type_id = TypeId::of::<T>();
+        let in_tables = self
+            .archetypes
+            .iter()
+            .any(|archetype| !archetype.is_empty() && archetype.contains(type_id));
+        let in_sparse_set = self.sparse_set::<T>().is_some_and(|set| !set.is_empty());
+        if in_tables || in_sparse_set {
+            return Err(ComponentError::StorageInUse(type_name::<T>().to_string()));
+        }
+
+        match storage {
+            StorageType::Table => {
+                self.sparse_sets.remove(&type_id);
+            }
+            StorageType::SparseSet => {
+                self.sparse_sets
+                    .insert(type_id, Box::new(SparseSet::<T>::default()));
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn storage_type<T: Component>(&self) -> StorageType {
+        if self.sparse_sets.contains_key(&TypeId::of::<T>()) {
+            StorageType::SparseSet
+        } else {
+            StorageType::Table
+        }
+    }
+
+    pub fn sparse_set<T: Component>(&self) -> Option<&SparseSet<T>> {
+        self.sparse_sets
+            .get(&TypeId::of::<T>())?
+            .as_any()
+            .downcast_ref::<SparseSet<T>>()
+    }
+
+    pub(crate) fn sparse_set_mut<T: Component>(&mut self) -> Option<&mut SparseSet<T>> {
+        self.sparse_sets
+            .get_mut(&TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+    }
+
+    // Opts a component into snapshots, `name` is the key its values are
+    // stored under.
+    pub fn register_serializable<T>(&mut self, name: &'static str) -> &mut Self
     where
-        Self: ComponentStorage<T>,
+        T: Component + Serialize + DeserializeOwned,
     {
+        self.serializable.register::<T>(name);
+        self
+    }
+
+    pub fn serializable(&self) -> &SnapshotRegistry {
+        &self.serializable
+    }
+
+    pub fn snapshot(&self) -> Result<WorldSnapshot, SnapshotError> {
+        let mut components = Vec::new();
+        for entity_id in self.entities.iter() {
+            let mut entity = EntitySnapshot {
+                id: entity_id,
+                components: Default::default(),
+            };
+            for (type_id, type_name) in self.component_types(entity_id) {
+                let serializable = self.serializable.get(type_id).ok_or(
+                    SnapshotError::UnregisteredComponent {
+                        component: type_name,
+                        entity: entity_id,
+                    },
+                )?;
+                if let Some(value) = (serializable.serialize)(self, entity_id) {
+                    let value = value
+                        .map_err(|err| SnapshotError::Serde(serializable.name.to_string(), err))?;
+                    entity
+                        .components
+                        .insert(serializable.name.to_string(), value);
+                }
+            }
+            components.push(entity);
+        }
+
+        Ok(WorldSnapshot {
+            entities: self.entities.snapshot(),
+            components,
+        })
+    }
+
+    // Replaces every entity and component with the snapshot's contents.
+    // Resources, events and registrations are kept. The snapshot is fully
+    // validated first, on error the world is left untouched. Hooks do not run,
+    // whatever they did when the snapshot was taken is already part of it.
+    pub fn restore(&mut self, snapshot: &WorldSnapshot) -> Result<(), SnapshotError> {
+        let entities =
+            Entities::restore(&snapshot.entities).ok_or(SnapshotError::InvalidEntities)?;
+        let mut inserts = Vec::new();
+        for entity in &snapshot.components {
+            if !entities.is_alive(entity.id) {
+                return Err(SnapshotError::DeadEntity(entity.id));
+            }
+            for (name, value) in &entity.components {
+                let serializable = self
+                    .serializable
+                    .get_by_name(name)
+                    .ok_or_else(|| SnapshotError::UnknownComponent(name.clone()))?;
+                let insert = (serializable.deserialize)(value)
+                    .map_err(|err| SnapshotError::Serde(name.clone(), err))?;
+                inserts.push((entity.id, insert));
+            }
+        }
+
+        self.archetypes = Archetypes::default();
+        for set in self.sparse_sets.values_mut() {
+            set.clear();
+        }
+        self.removed.clear();
+        self.spatial.get_mut().clear();
+        self.deferred = Commands::default();
+        self.hook_commands = Commands::default();
+        self.entities = entities;
+        let alive: Vec<EntityId> = self.entities.iter().collect();
+        for entity_id in alive {
+            self.place_in_empty_archetype(entity_id);
+        }
+        let hooks = std::mem::take(&mut self.hooks);
+        for (entity_id, insert) in inserts {
+            insert(self, entity_id);
+        }
+        self.hooks = hooks;
+
+        Ok(())
+    }
+
+    pub fn register_reflect<T: Reflect>(&mut self, name: &'static str) -> &mut Self {
+        self.reflectable.register::<T>(name);
+        self
+    }
+
+    pub fn reflectable(&self) -> &ReflectRegistry {
+        &self.reflectable
+    }
+
+    // Every reflected component attached to the entity, sorted by name.
+    pub fn reflect_components(&self, entity_id: EntityId) -> Vec<(&'static str, &dyn Reflect)> {
+        let mut components: Vec<(&'static str, &dyn Reflect)> = self
+            .component_types(entity_id)
+            .into_iter()
+            .filter_map(|(type_id, _)| {
+                let reflect = self.reflectable.get(type_id)?;
+                Some((reflect.name, (reflect.get)(self, entity_id)?))
+            })
+            .collect();
+        components.sort_by_key(|(name, _)| *name);
+
+        components
+    }
+
+    // Every component attached to the entity, by its reflect or snapshot name
+    // when registered and by its short type name otherwise.
+    pub fn component_names(&self, entity_id: EntityId) -> Vec<&'static str> {
+        let mut names: Vec<&'static str> = self
+            .component_types(entity_id)
+            .into_iter()
+            .map(|(type_id, type_name)| {
+                self.reflectable
+                    .name_of(type_id)
+                    .or_else(|| self.serializable.name_of(type_id))
+                    .unwrap_or_else(|| {
+                        let path = type_name.split('<').next().unwrap_or(type_name);
+                        path.rsplit("::").next().unwrap_or(path)
+                    })
+            })
+            .collect();
+        names.sort_unstable();
+
+        names
+    }
+
+    pub fn reflect(&self, entity_id: EntityId, component: &str) -> Result<&dyn Reflect, ReflectError> {
+        let reflect = self
+            .reflectable
+            .get_by_name(component)
+            .ok_or_else(|| ReflectError::UnknownComponent(component.to_string()))?;
+
+        (reflect.get)(self, entity_id)
+            .ok_or_else(|| ReflectError::NotAttached(component.to_string(), self.label(entity_id)))
+    }
+
+    // Marks the component as changed, like `get_mut`.
+    pub fn reflect_mut(
+        &mut self,
+        entity_id: EntityId,
+        component: &str,
+    ) -> Result<&mut dyn Reflect, ReflectError> {
+        let reflect = self
+            .reflectable
+            .get_by_name(component)
+            .ok_or_else(|| ReflectError::UnknownComponent(component.to_string()))?;
+        let (get, get_mut) = (reflect.get, reflect.get_mut);
+        // Checked up front, the label cannot be read while `get_mut` borrows
+        // the world.
+        if get(self, entity_id).is_none() {
+            return Err(ReflectError::NotAttached(
+                component.to_string(),
+                self.label(entity_id),
+            ));
+        }
+
+        Ok(get_mut(self, entity_id).expect("component checked above"))
+    }
+
+    pub fn register_prefab(&mut self, name: &str, prefab: Prefab) -> &mut Self {
+        self.resource_or_insert_with(Prefabs::default)
+            .insert(name, prefab);
+        self
+    }
+
+    pub fn has_prefab(&self, name: &str) -> bool {
+        self.resource::<Prefabs>()
+            .is_some_and(|prefabs| prefabs.contains(name))
+    }
+
+    pub fn instantiate(
+        &mut self,
+        name: &str,
+        overrides: PrefabOverrides,
+    ) -> Result<PrefabInstance, PrefabError> {
+        let prefab = self
+            .resource::<Prefabs>()
+            .and_then(|prefabs| prefabs.get(name))
+            .ok_or_else(|| PrefabError::NotFound(name.to_string()))?;
+
+        prefab.instantiate(self, overrides)
+    }
+
+    fn component_types(&self, entity_id: EntityId) -> Vec<(TypeId, &'static str)> {
+        let mut types: Vec<(TypeId, &'static str)> = self
+            .sparse_sets
+            .iter()
+            .filter(|(_, set)| set.contains(entity_id))
+            .map(|(type_id, set)| (*type_id, set.type_name()))
+            .collect();
+        if let Some(archetype) = self.entity_archetype(entity_id) {
+            types.extend(archetype.types().iter().filter_map(|type_id| {
+                Some((*type_id, archetype.column_type_name(*type_id)?))
+            }));
+        }
+
+        types
+    }
+
+    pub fn archetypes(&self) -> &Archetypes {
+        &self.archetypes
+    }
+
+    pub(crate) fn archetypes_mut(&mut self) -> &mut Archetypes {
+        &mut self.archetypes
+    }
+
+    pub fn entity_location(&self, entity_id: EntityId) -> Option<EntityLocation> {
+        self.entities.location(entity_id)
+    }
+
+    pub fn entity_archetype(&self, entity_id: EntityId) -> Option<&Archetype> {
+        self.archetypes.get(self.entity_location(entity_id)?.archetype)
+    }
+
+    pub fn iter_component<T: Component>(&self) -> impl Iterator<Item = (EntityId, &T)> {
+        let tables = self
+            .archetypes
+            .iter()
+            .filter_map(|archetype| Some((archetype.entities(), archetype.column::<T>()?)))
+            .flat_map(|(entities, column)| entities.iter().copied().zip(column.data()));
+
+        self.sparse_set::<T>()
+            .into_iter()
+            .flat_map(|set| set.iter())
+            .chain(tables)
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.entities.iter()
+    }
+
+    pub fn name(&self, entity_id: EntityId) -> Option<&str> {
+        self.fetch::<Name>(&entity_id).map(Name::as_str)
+    }
+
+    pub fn label(&self, entity_id: EntityId) -> EntityLabel {
+        EntityLabel {
+            id: entity_id,
+            name: self.name(entity_id).map(str::to_string),
+        }
+    }
+
+    // First entity carrying the given `Name`.
+    pub fn find_by_name(&self, name: &str) -> Option<EntityId> {
+        self.iter_component::<Name>()
+            .find(|(_, entity_name)| entity_name.as_str() == name)
+            .map(|(entity_id, _)| entity_id)
+    }
+
+    // Tags are zero-sized marker components, `struct Landed; impl Component
+    // for Landed {}`, so only their presence is stored.
+    pub fn entities_with_tag<T: Component>(&self) -> impl Iterator<Item = EntityId> + '_ {
+        const { assert!(size_of::<T>() == 0, "tags must be zero-sized components") };
+        self.iter_component::<T>().map(|(entity_id, _)| entity_id)
+    }
+
+    pub fn is_alive(&self, entity_id: EntityId) -> bool {
+        self.entities.is_alive(entity_id)
+    }
+
+    pub fn ensure_alive(&self, entity_id: EntityId) -> Result<(), ComponentError> {
+        if self.is_alive(entity_id) {
+            Ok(())
+        } else {
+            Err(ComponentError::DeadEntity(entity_id))
+        }
+    }
+
+    pub fn query<Q: QueryData>(&mut self) -> QueryIter<'_, Q, ()> {
+        QueryIter::new(self)
+    }
+
+    pub fn query_filtered<Q: QueryData, F: QueryFilter>(&mut self) -> QueryIter<'_, Q, F> {
+        QueryIter::new(self)
+    }
+
+    pub fn spawn(&mut self) -> EntityId {
+        let entity_id = self.entities.alloc();
+        self.place_in_empty_archetype(entity_id);
+
+        entity_id
+    }
+
+    fn place_in_empty_archetype(&mut self, entity_id: EntityId) {
+        let row = self.archetypes.push_entity(EMPTY_ARCHETYPE, entity_id);
+        self.entities.set_location(
+            entity_id,
+            EntityLocation {
+                archetype: EMPTY_ARCHETYPE,
+                row,
+            },
+        );
+    }
+
+    pub fn spawn_with<B: Bundle>(&mut self, bundle: B) -> EntityId {
+        let entity_id = self.spawn();
+        bundle.insert_into(self, entity_id);
+
+        entity_id
+    }
+
+    pub fn build(&mut self) -> EntityBuilder<'_> {
+        EntityBuilder::new(self)
+    }
+
+    pub fn add_bundle<B: Bundle>(&mut self, entity_id: EntityId, bundle: B) -> &mut Self {
+        bundle.insert_into(self, entity_id);
+
+        self
+    }
+
+    pub fn try_add_component<T: Component>(
+        &mut self,
+        entity_id: EntityId,
+        component: T,
+    ) -> Result<Option<T>, ComponentError> {
+        self.with_hooks(|world| {
+            let previous = world.insert_component(entity_id, component)?;
+            let lifecycle = match previous {
+                Some(_) => Lifecycle::Replaced,
+                None => Lifecycle::Added,
+            };
+            world.run_hooks(&[TypeId::of::<T>()], lifecycle, entity_id);
+
+            Ok(previous)
+        })
+    }
+
+    fn insert_component<T: Component>(
+        &mut self,
+        entity_id: EntityId,
+        component: T,
+    ) -> Result<Option<T>, ComponentError> {
+        self.mark_entity_written(TypeId::of::<T>(), entity_id);
+        let location = self
+            .entities
+            .location(entity_id)
+            .ok_or(ComponentError::DeadEntity(entity_id))?;
+        let tick = self.change_tick;
+
+        if let Some(set) = self.sparse_set_mut::<T>() {
+            return Ok(set.insert(entity_id, component, tick));
+        }
+
+        if let Some(column) = self
+            .archetypes
+            .get_mut(location.archetype)
+            .and_then(|archetype| archetype.column_mut::<T>())
+        {
+            return Ok(Some(column.replace(location.row, component, tick)));
+        }
+
+        let target = self.archetypes.with_component::<T>(location.archetype);
+        let moved = self
+            .archetypes
+            .move_entity(location, target, |_, _, _| unreachable!());
+        self.archetypes
+            .get_mut(target)
+            .and_then(|archetype| archetype.column_mut::<T>())
+            .expect("target archetype is missing the added column")
+            .push(
+                component,
+                ComponentTicks {
+                    added: tick,
+                    changed: tick,
+                },
+            );
+        self.relocate(entity_id, location, target, moved.row, moved.swapped);
+
+        Ok(None)
+    }
+
+    pub fn fetch<T: Component>(&self, id: &EntityId) -> Option<&T> {
         <Self as ComponentStorage<T>>::fetch(self, id)
     }
 
-    pub fn get_mut<T: Component>(&mut self, id: &EntityId) -> Option<&mut T>
-    where
-        Self: ComponentStorage<T>,
-    {
+    pub fn get_mut<T: Component>(&mut self, id: &EntityId) -> Option<Mut<'_, T>> {
         <Self as ComponentStorage<T>>::get_mut(self, id)
     }
 
+    pub fn remove_component<T: Component>(&mut self, entity_id: &EntityId) -> Option<T> {
+        self.with_hooks(|world| world.take_component::<T>(entity_id))
+    }
+
+    fn take_component<T: Component>(&mut self, entity_id: &EntityId) -> Option<T> {
+        let type_id = TypeId::of::<T>();
+        if !self.has_component::<T>(entity_id) {
+            return None;
+        }
+        self.run_hooks(&[type_id], Lifecycle::Removed, *entity_id);
+        self.mark_entity_written(type_id, *entity_id);
+
+        if self.sparse_sets.contains_key(&type_id) {
+            let removed = self.sparse_set_mut::<T>()?.remove(*entity_id)?;
+            self.record_removed(*entity_id, type_id);
+            return Some(removed);
+        }
+
+        let location = self.entities.location(*entity_id)?;
+        if !self.archetypes.get(location.archetype)?.contains(type_id) {
+            return None;
+        }
+
+        let target = self.archetypes.without_component(location.archetype, type_id);
+        let mut removed = None;
+        let moved = self.archetypes.move_entity(
+            location,
+            target,
+            |_, column: &mut dyn AnyColumn, row| {
+                let column = column
+                    .as_any_mut()
+                    .downcast_mut::<Column<T>>()
+                    .expect("removed column has a foreign type");
+                removed = Some(column.swap_remove(row).0);
+            },
+        );
+        self.relocate(*entity_id, location, target, moved.row, moved.swapped);
+        self.record_removed(*entity_id, type_id);
+
+        removed
+    }
+
+    pub fn has_component<T: Component>(&self, entity_id: &EntityId) -> bool {
+        if let Some(set) = self.sparse_set::<T>() {
+            return set.contains(*entity_id);
+        }
+        self.entity_archetype(*entity_id)
+            .is_some_and(|archetype| archetype.contains(TypeId::of::<T>()))
+    }
+
+    fn relocate(
+        &mut self,
+        entity_id: EntityId,
+        previous: EntityLocation,
+        archetype: ArchetypeId,
+        row: usize,
+        swapped: Option<EntityId>,
+    ) {
+        self.entities
+            .set_location(entity_id, EntityLocation { archetype, row });
+        if let Some(swapped) = swapped {
+            self.entities.set_location(swapped, previous);
+        }
+    }
+
+    fn record_removed(&mut self, entity_id: EntityId, type_id: TypeId) {
+        self.removed
+            .entry(type_id)
+            .or_default()
+            .push((entity_id, self.change_tick));
+    }
+
     pub fn set_parent(&mut self, entity_id: EntityId, parent_id: EntityId) {
-        let child = self
-            .children
-            .entry(parent_id)
-            .or_insert(Children::default());
-        child.add_entity(entity_id);
-        self.parent.insert(entity_id, Parent { entity: parent_id });
+        if !self.is_alive(entity_id) || !self.is_alive(parent_id) {
+            warn!("cannot parent {entity_id} to {parent_id}: entity is not alive");
+            return;
+        }
+        if entity_id == parent_id || self.is_ancestor(entity_id, parent_id) {
+            warn!("cannot parent {entity_id} to {parent_id}: hierarchy would contain a cycle");
+            return;
+        }
+
+        self.remove_parent(entity_id);
+        match self.get_mut::<Children>(&parent_id) {
+            Some(mut children) => children.add_entity(entity_id),
+            None => {
+                let mut children = Children::default();
+                children.add_entity(entity_id);
+                self.add_component(parent_id, children);
+            }
+        }
+        self.add_component(entity_id, Parent { entity: parent_id });
     }
 
+    pub fn remove_parent(&mut self, entity_id: EntityId) -> Option<EntityId> {
+        let parent = self.remove_component::<Parent>(&entity_id)?;
+        if let Some(mut children) = self.get_mut::<Children>(&parent.entity) {
+            children.remove_entity(&entity_id);
+        }
+
+        Some(parent.entity)
+    }
+
+    pub fn reparent(&mut self, entity_id: EntityId, parent_id: Option<EntityId>) {
+        match parent_id {
+            Some(parent_id) => self.set_parent(entity_id, parent_id),
+            None => {
+                self.remove_parent(entity_id);
+            }
+        }
+    }
+
+    pub fn is_ancestor(&self, ancestor_id: EntityId, entity_id: EntityId) -> bool {
+        let mut current = self.fetch::<Parent>(&entity_id);
+        while let Some(parent) = current {
+            if parent.entity == ancestor_id {
+                return true;
+            }
+            current = self.fetch::<Parent>(&parent.entity);
+        }
+
+        false
+    }
+
+    pub fn descendants(&self, entity_id: EntityId) -> Vec<EntityId> {
+        let mut descendants = Vec::new();
+        let mut pending = vec![entity_id];
+        while let Some(current) = pending.pop() {
+            if let Some(children) = self.fetch::<Children>(&current) {
+                for &child in children.entities() {
+                    descendants.push(child);
+                    pending.push(child);
+                }
+            }
+        }
+
+        descendants
+    }
+
+    // Screen position, negative coordinates are clamped to 0.
     pub fn get_abs_pos(&self, entity_id: EntityId) -> Result<RenderPosition, ComponentError> {
-        let entity_pos = self
-            .positions
-            .get(&entity_id)
-            .ok_or_else(|| ComponentError::NotAttached("Position".to_string(), entity_id))?;
-        let mut pos = (entity_pos.x as usize, entity_pos.y as usize);
-        if entity_pos.position_type == PositionType::Rel {
-            if let Some(parent) = self.parent.get(&entity_id) {
-                let parent_pos = self.get_abs_pos(parent.entity)?;
-                pos = (pos.0 + parent_pos.0, pos.1 + parent_pos.1);
+        let pos = self.world_position(entity_id)?;
+
+        Ok((pos.x as usize, pos.y as usize))
+    }
+
+    pub fn world_position(&self, entity_id: EntityId) -> Result<Vec2, ComponentError> {
+        Ok(self.world_placement(entity_id)?.0)
+    }
+
+    // Position and rotation in degrees in world space. A `Rel` position is an
+    // offset from the parent, turned by the parent's rotation.
+    //
+    // Collision and rendering resolve `Position` here on every read rather
+    // than reading `GlobalTransform`: games move entities in Update and check
+    // collisions right away, while `GlobalTransform` is only current after
+    // `propagate_transforms` ran in PostUpdate. `Transform` entities are not
+    // placed by this.
+    fn world_placement(&self, entity_id: EntityId) -> Result<(Vec2, f32), ComponentError> {
+        self.ensure_alive(entity_id)?;
+        let mut pos = Vec2::ZERO;
+        let mut rotation = 0.0;
+        let mut visited = Vec::new();
+        let mut current = entity_id;
+        loop {
+            let entity_pos = self.fetch::<Position>(&current).ok_or_else(|| {
+                ComponentError::NotAttached("Position".to_string(), self.label(current))
+            })?;
+            pos += Vec2::new(entity_pos.x, entity_pos.y);
+            rotation += self.rotation_of(current);
+            if entity_pos.position_type != PositionType::Rel {
+                break;
+            }
+            let Some(parent) = self.fetch::<Parent>(&current) else {
+                break;
+            };
+
+            if !self.is_alive(parent.entity) {
+                return Err(ComponentError::DanglingParent(self.label(current)));
+            }
+            visited.push(current);
+            if visited.contains(&parent.entity) {
+                return Err(ComponentError::HierarchyCycle(self.label(parent.entity)));
             }
+            current = parent.entity;
+            pos = Vec2::from_angle(self.rotation_of(current).to_radians()).rotate(pos);
         }
 
-        Ok(pos)
+        Ok((pos, rotation))
     }
 
-    pub fn get_collision(&self, entity_id: EntityId) -> Result<CollisionInfo, ComponentError> {
-        let mut info = CollisionInfo::default();
-        let collider = self
-            .colliders
-            .get(&entity_id)
-            .ok_or_else(|| ComponentError::NotAttached("Collider".to_string(), entity_id))?;
-        let childs = if let Some(child_component) = self.children.get(&entity_id) {
-            child_component.entities()
-        } else {
-            &HashSet::new()
+    fn rotation_of(&self, entity_id: EntityId) -> f32 {
+        self.fetch::<Rotation>(&entity_id)
+            .map_or(0.0, |rotation| rotation.x as f32)
+    }
+
+    pub fn set_spatial_cell_size(&mut self, cell_size: usize) -> &mut Self {
+        *self.spatial.get_mut() = SpatialGrid::new(cell_size);
+
+        self
+    }
+
+    // Collider bounds indexed for the broadphase. Writes to `Position`,
+    // `Rotation`, `Collider` or `Parent` mark the written entity, the next read
+    // re-indexes it together with its descendants. Colliders without an
+    // absolute position are left out.
+    pub fn spatial_grid(&self) -> Ref<'_, SpatialGrid> {
+        // A clean grid is only read, callers may still hold an earlier `Ref`.
+        if !self.spatial.borrow().is_dirty() {
+            return self.spatial.borrow();
+        }
+        let (rebuild, pending) = {
+            let mut grid = self.spatial.borrow_mut();
+            (grid.needs_rebuild(), grid.take_pending())
         };
-        let pos = self.get_abs_pos(entity_id);
-        let ((min_x, min_y), (max_x, max_y)) = collider.get_points(pos?);
-
-        for (&other_id, other_collider) in &self.colliders {
-            if other_id != entity_id && !childs.contains(&other_id) {
-                let other_pos = self.get_abs_pos(other_id);
-                let ((other_min_x, other_min_y), (other_max_x, other_max_y)) =
-                    other_collider.get_points(other_pos?);
-
-                if !(max_x < other_min_x
-                    || min_x > other_max_x
-                    || max_y < other_min_y
-                    || min_y > other_max_y)
-                {
-                    let overlap_x =
-                        (max_x.min(other_max_x)) as isize - (min_x.max(other_min_x)) as isize;
-                    let overlap_y =
-                        (max_y.min(other_max_y)) as isize - (min_y.max(other_min_y)) as isize;
-
-                    let mut sides = HashSet::new();
-
-                    let fully_inside = min_x > other_min_x
-                        && max_x < other_max_x
-                        && min_y > other_min_y
-                        && max_y < other_max_y;
-
-                    if fully_inside {
-                        let rel_x = min_x - other_min_x;
-                        let rel_y = min_y - other_min_y;
-                        sides.insert(CollisionSide::Contained { x: rel_x, y: rel_y });
-                    } else {
-                        if overlap_x > 0 {
-                            if (min_y <= other_min_y && max_y > other_min_y)
-                                || (min_y <= other_max_y && max_y > other_max_y)
-                            {
-                                sides.insert(CollisionSide::Top(overlap_y as usize));
-                            }
-
-                            if (max_y >= other_max_y && min_y < other_max_y)
-                                || (max_y > other_min_y && min_y < other_min_y)
-                            {
-                                sides.insert(CollisionSide::Bottom(overlap_y as usize));
-                            }
-                        }
-
-                        if (min_x <= other_max_x && max_x > other_max_x)
-                            || (min_x <= other_min_x && max_x > other_min_x)
-                        {
-                            sides.insert(CollisionSide::Left(overlap_x as usize));
-                        }
-
-                        if (max_x >= other_min_x && min_x < other_min_x)
-                            || (max_x >= other_max_x && min_x < other_max_x)
-                        {
-                            sides.insert(CollisionSide::Right(overlap_x as usize));
-                        }
-                    }
 
-                    info.add_collision(other_id, sides);
+        if rebuild {
+            let bounds: Vec<(EntityId, Aabb)> = self
+                .iter_component::<Collider>()
+                .filter_map(|(entity_id, _)| Some((entity_id, self.indexed_bounds(entity_id)?)))
+                .collect();
+            let live: HashSet<EntityId> = bounds.iter().map(|(entity_id, _)| *entity_id).collect();
+
+            let mut grid = self.spatial.borrow_mut();
+            grid.retain(|entity_id| live.contains(&entity_id));
+            for (entity_id, aabb) in bounds {
+                grid.insert(entity_id, aabb);
+            }
+            grid.mark_clean();
+        } else if !pending.is_empty() {
+            let mut moved = HashSet::new();
+            for entity_id in pending {
+                moved.insert(entity_id);
+                moved.extend(self.descendants(entity_id));
+            }
+            let bounds: Vec<(EntityId, Option<Aabb>)> = moved
+                .into_iter()
+                .map(|entity_id| (entity_id, self.indexed_bounds(entity_id)))
+                .collect();
+
+            let mut grid = self.spatial.borrow_mut();
+            for (entity_id, aabb) in bounds {
+                match aabb {
+                    Some(aabb) => grid.insert(entity_id, aabb),
+                    None => {
+                        grid.remove(entity_id);
+                    }
                 }
             }
+            grid.mark_clean();
+        }
+
+        self.spatial.borrow()
+    }
+
+    fn indexed_bounds(&self, entity_id: EntityId) -> Option<Aabb> {
+        let collider = self.fetch::<Collider>(&entity_id)?;
+        let (pos, rotation) = self.world_placement(entity_id).ok()?;
+
+        Some(collider.bounds(pos, rotation))
+    }
+
+    fn affects_spatial(type_id: TypeId) -> bool {
+        type_id == TypeId::of::<Position>()
+            || type_id == TypeId::of::<Rotation>()
+            || type_id == TypeId::of::<Collider>()
+            || type_id == TypeId::of::<Parent>()
+    }
+
+    // The grid `Mut` guards of `type_id` report their writes to, if it is
+    // indexed at all.
+    pub(crate) fn spatial_for(&self, type_id: TypeId) -> Option<*const RefCell<SpatialGrid>> {
+        Self::affects_spatial(type_id).then_some(&*self.spatial as *const _)
+    }
+
+    fn mark_entity_written(&mut self, type_id: TypeId, entity_id: EntityId) {
+        if Self::affects_spatial(type_id) {
+            self.spatial.get_mut().mark_entity_dirty(entity_id);
+        }
+    }
+
+    // Collider in world space, placed at the entity's world position and
+    // turned by its own and its `Rel` ancestors' `Rotation`.
+    pub fn collider_shape(&self, entity_id: EntityId) -> Result<RoundedConvex, ComponentError> {
+        let collider = self.fetch::<Collider>(&entity_id).ok_or_else(|| {
+            ComponentError::NotAttached("Collider".to_string(), self.label(entity_id))
+        })?;
+
+        let (pos, rotation) = self.world_placement(entity_id)?;
+
+        Ok(collider.to_convex(pos, rotation))
+    }
+
+    pub fn collider_bounds(&self, entity_id: EntityId) -> Result<Aabb, ComponentError> {
+        let collider = self.fetch::<Collider>(&entity_id).ok_or_else(|| {
+            ComponentError::NotAttached("Collider".to_string(), self.label(entity_id))
+        })?;
+
+        let (pos, rotation) = self.world_placement(entity_id)?;
+
+        Ok(collider.bounds(pos, rotation))
+    }
+
+    pub fn get_collision(&self, entity_id: EntityId) -> Result<CollisionInfo, ComponentError> {
+        let mut info = CollisionInfo::default();
+        let aabb = self.collider_bounds(entity_id)?;
+        let shape = self.collider_shape(entity_id)?;
+
+        let grid = self.spatial_grid();
+        for other_id in grid.query(aabb) {
+            if other_id == entity_id
+                || self.is_child_of(other_id, entity_id)
+                || !self.can_collide(entity_id, other_id)
+            {
+                continue;
+            }
+            let Ok(other_shape) = self.collider_shape(other_id) else {
+                continue;
+            };
+            if let Some(manifold) = intersect(&shape, &other_shape) {
+                let sensor = self.is_sensor(entity_id) || self.is_sensor(other_id);
+                info.add_collision(other_id, manifold, sensor);
+            }
         }
 
         Ok(info)
     }
 
+    // `get_collision` for every indexed collider at once, each overlapping
+    // pair is tested a single time.
+    pub fn collect_collisions(&self) -> HashMap<EntityId, CollisionInfo> {
+        let grid = self.spatial_grid();
+        let mut collisions: HashMap<EntityId, CollisionInfo> = grid
+            .entities()
+            .map(|entity_id| (entity_id, CollisionInfo::default()))
+            .collect();
+        let mut shapes: HashMap<EntityId, RoundedConvex> = HashMap::new();
+
+        for (a, b) in grid.pairs() {
+            if !self.can_collide(a, b) {
+                continue;
+            }
+            for entity_id in [a, b] {
+                if !shapes.contains_key(&entity_id)
+                    && let Ok(shape) = self.collider_shape(entity_id)
+                {
+                    shapes.insert(entity_id, shape);
+                }
+            }
+            let (Some(shape_a), Some(shape_b)) = (shapes.get(&a), shapes.get(&b)) else {
+                continue;
+            };
+            let Some(manifold) = intersect(shape_a, shape_b) else {
+                continue;
+            };
+            let sensor = self.is_sensor(a) || self.is_sensor(b);
+
+            if !self.is_child_of(a, b)
+                && let Some(info) = collisions.get_mut(&b)
+            {
+                info.add_collision(a, manifold.flipped(), sensor);
+            }
+            if !self.is_child_of(b, a)
+                && let Some(info) = collisions.get_mut(&a)
+            {
+                info.add_collision(b, manifold, sensor);
+            }
+        }
+
+        collisions
+    }
+
+    // Layer masks and ignore lists, the same answer both ways round.
+    fn can_collide(&self, entity_id: EntityId, other_id: EntityId) -> bool {
+        let (Some(collider), Some(other)) = (
+            self.fetch::<Collider>(&entity_id),
+            self.fetch::<Collider>(&other_id),
+        ) else {
+            return false;
+        };
+        let ignores = |id: EntityId, ignored: EntityId| {
+            self.fetch::<CollisionIgnore>(&id).is_some_and(|ignore| ignore.contains(&ignored))
+        };
+
+        collider.interacts_with(other)
+            && !ignores(entity_id, other_id)
+            && !ignores(other_id, entity_id)
+    }
+
+    fn is_sensor(&self, entity_id: EntityId) -> bool {
+        self.fetch::<Collider>(&entity_id).is_some_and(|collider| collider.sensor)
+    }
+
+    fn is_child_of(&self, entity_id: EntityId, parent_id: EntityId) -> bool {
+        self.fetch::<Children>(&parent_id)
+            .is_some_and(|children| children.entities().contains(&entity_id))
+    }
+
     pub fn remove_entity(&mut self, entity_id: EntityId) {
-        self.positions.remove(&entity_id);
-        self.sizes.remove(&entity_id);
-        self.colliders.remove(&entity_id);
-        self.rotation.remove(&entity_id);
-        if let Some(child_component) = self.children.get(&entity_id) {
-            for child in child_component.entities() {
-                self.parent.remove(child);
+        self.despawn(entity_id, DespawnMode::Orphan);
+    }
+
+    pub fn despawn_recursive(&mut self, entity_id: EntityId) {
+        self.despawn(entity_id, DespawnMode::Recursive);
+    }
+
+    pub fn despawn(&mut self, entity_id: EntityId, mode: DespawnMode) {
+        self.with_hooks(|world| world.despawn_entities(entity_id, mode));
+    }
+
+    fn despawn_entities(&mut self, entity_id: EntityId, mode: DespawnMode) {
+        if !self.is_alive(entity_id) {
+            return;
+        }
+
+        self.remove_parent(entity_id);
+        let doomed = match mode {
+            DespawnMode::Orphan => {
+                if let Some(children) = self.remove_component::<Children>(&entity_id) {
+                    for child in children.entities() {
+                        self.remove_component::<Parent>(child);
+                    }
+                }
+                vec![entity_id]
+            }
+            DespawnMode::Recursive => {
+                let mut doomed = self.descendants(entity_id);
+                doomed.push(entity_id);
+                doomed
+            }
+        };
+
+        for id in doomed {
+            self.spatial.get_mut().mark_entity_dirty(id);
+            if !self.hooks.is_empty() {
+                let types: Vec<TypeId> = self
+                    .component_types(id)
+                    .into_iter()
+                    .map(|(type_id, _)| type_id)
+                    .collect();
+                self.run_hooks(&types, Lifecycle::Removed, id);
+            }
+            let Some(location) = self.entities.location(id) else {
+                continue;
+            };
+            let (types, swapped) = self.archetypes.remove_entity(location);
+            if let Some(swapped) = swapped {
+                self.entities.set_location(swapped, location);
+            }
+            let sparse_types: Vec<TypeId> = self
+                .sparse_sets
+                .iter_mut()
+                .filter_map(|(type_id, set)| set.remove_drop(id).then_some(*type_id))
+                .collect();
+            for type_id in types.into_iter().chain(sparse_types) {
+                self.record_removed(id, type_id);
             }
-            self.children.remove(&entity_id);
+            self.entities.free(id);
         }
-        self.parent.remove(&entity_id);
     }
 }
@@ -1,8 +1,10 @@
+use crate::engine::ecs::schedule::Schedule;
 use crate::engine::ecs::world::World;
 use crate::engine::input::InputState;
 use super::render::renderer::Renderer;
 
 pub trait Game {
+    fn setup(&mut self, _schedule: &mut Schedule, _world: &mut World) {}
     fn update(&mut self, delta_time: f32, input_state: &InputState, world: &mut World);
     fn render(&self, renderer: &mut Renderer, world: &mut World);
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 use winit::keyboard::KeyCode;
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct InputState {
     keys_down: HashSet<KeyCode>,
     keys_pressed: HashSet<KeyCode>,
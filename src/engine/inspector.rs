@@ -0,0 +1,171 @@
+use crate::engine::ecs::component::{Parent, Position, Size};
+use crate::engine::ecs::world::{EntityId, World};
+use crate::engine::input::InputState;
+use crate::engine::render::rect::Rectangle;
+use crate::engine::render::renderer::Renderer;
+use crate::engine::render::text::Text;
+use crate::engine::render::{Point, Renderable};
+use winit::keyboard::KeyCode;
+
+const TEXT_SCALE: usize = 2;
+const PANEL_MARGIN: usize = 8;
+const PANEL_PADDING: usize = 6;
+
+// Debug overlay, works with any `Game`. While enabled it highlights the
+// entity under the cursor, draws its collider and lists its components.
+pub struct Inspector {
+    enabled: bool,
+    toggle_key: KeyCode,
+}
+
+impl Default for Inspector {
+    fn default() -> Self {
+        Self::new(KeyCode::F1)
+    }
+}
+
+impl Inspector {
+    pub fn new(toggle_key: KeyCode) -> Self {
+        Self {
+            enabled: false,
+            toggle_key,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn handle_input(&mut self, input_state: &InputState) {
+        if input_state.is_pressed(&self.toggle_key) {
+            self.toggle();
+        }
+    }
+
+    // Smallest entity whose bounds contain the cursor, so a child wins over
+    // the parent it sits in.
+    pub fn hovered(world: &World, cursor: (f64, f64)) -> Option<EntityId> {
+        if cursor.0 < 0.0 || cursor.1 < 0.0 {
+            return None;
+        }
+        let cursor = (cursor.0 as usize, cursor.1 as usize);
+
+        world
+            .iter_component::<Position>()
+            .filter_map(|(entity_id, _)| {
+                let (start, end) = Self::collider_bounds(world, entity_id)
+                    .or_else(|| Self::size_bounds(world, entity_id))?;
+                let contains = (start.0..=end.0).contains(&cursor.0)
+                    && (start.1..=end.1).contains(&cursor.1);
+                contains.then(|| (entity_id, (end.0 - start.0) * (end.1 - start.1)))
+            })
+            .min_by_key(|(_, area)| *area)
+            .map(|(entity_id, _)| entity_id)
+    }
+
+    pub fn render(&self, renderer: &mut Renderer, world: &World, cursor: (f64, f64)) {
+        if !self.enabled {
+            return;
+        }
+
+        let hovered = Self::hovered(world, cursor);
+        if let Some(entity_id) = hovered {
+            if let Some((start, end)) = Self::size_bounds(world, entity_id) {
+                Self::outline(renderer, start, end, Renderer::rgb_to_u32(255, 221, 0));
+            }
+            if let Some((start, end)) = Self::collider_bounds(world, entity_id) {
+                Self::outline(renderer, start, end, Renderer::rgb_to_u32(255, 64, 64));
+            }
+        }
+
+        let lines = match hovered {
+            Some(entity_id) => Self::describe(world, entity_id),
+            None => vec!["INSPECTOR: NO ENTITY UNDER CURSOR".to_string()],
+        };
+        self.draw_panel(renderer, &lines);
+    }
+
+    fn describe(world: &World, entity_id: EntityId) -> Vec<String> {
+        let mut lines = vec![format!("ENTITY {}", world.label(entity_id))];
+        if let Some(parent) = world.fetch::<Parent>(&entity_id) {
+            lines.push(format!("PARENT {}", world.label(parent.entity)));
+        }
+
+        let reflected = world.reflect_components(entity_id);
+        for name in world.component_names(entity_id) {
+            match reflected.iter().find(|(reflect_name, _)| *reflect_name == name) {
+                Some((_, component)) => {
+                    let fields: Vec<String> = component
+                        .fields()
+                        .iter()
+                        .filter_map(|field| {
+                            Some(format!("{}={}", field.name, component.field(field.name)?))
+                        })
+                        .collect();
+                    lines.push(format!("{name} {}", fields.join(" ")));
+                }
+                None => lines.push(name.to_string()),
+            }
+        }
+
+        lines
+    }
+
+    fn draw_panel(&self, renderer: &mut Renderer, lines: &[String]) {
+        let width = lines
+            .iter()
+            .map(|line| Text::measure(line.chars().count(), TEXT_SCALE).0)
+            .max()
+            .unwrap_or(0)
+            + PANEL_PADDING * 2;
+        let height = lines.len() * Text::line_height(TEXT_SCALE) + PANEL_PADDING * 2;
+        let width = width.min(renderer.width().saturating_sub(PANEL_MARGIN + 1));
+        let height = height.min(renderer.height().saturating_sub(PANEL_MARGIN + 1));
+
+        Rectangle::new(
+            (PANEL_MARGIN, PANEL_MARGIN),
+            (width, height),
+            Renderer::rgb_to_u32(16, 16, 24),
+        )
+        .with_outline(Renderer::rgb_to_u32(96, 96, 120))
+        .fill(renderer);
+
+        for (idx, line) in lines.iter().enumerate() {
+            Text::new(
+                (
+                    PANEL_MARGIN + PANEL_PADDING,
+                    PANEL_MARGIN + PANEL_PADDING + idx * Text::line_height(TEXT_SCALE),
+                ),
+                line,
+                Renderer::rgb_to_u32(232, 232, 232),
+            )
+            .with_scale(TEXT_SCALE)
+            .render(renderer);
+        }
+    }
+
+    fn outline(renderer: &mut Renderer, start: Point, end: Point, color: u32) {
+        Rectangle::new(start, (end.0 - start.0, end.1 - start.1), color)
+            .with_outline(color)
+            .outline(renderer);
+    }
+
+    // Clamped to the window, the part left of or above it is not drawn.
+    fn collider_bounds(world: &World, entity_id: EntityId) -> Option<(Point, Point)> {
+        let ((min_x, min_y), (max_x, max_y)) = world.collider_bounds(entity_id).ok()?;
+        let clamp = |value: isize| value.max(0) as usize;
+
+        Some(((clamp(min_x), clamp(min_y)), (clamp(max_x), clamp(max_y))))
+    }
+
+    fn size_bounds(world: &World, entity_id: EntityId) -> Option<(Point, Point)> {
+        let size = world.fetch::<Size>(&entity_id)?;
+        let start = world.get_abs_pos(entity_id).ok()?;
+
+        Some((start, (start.0 + size.x, start.1 + size.y)))
+    }
+}
@@ -3,4 +3,6 @@ pub mod game;
 pub mod input;
 pub mod ecs;
 pub mod math;
-pub mod resource;
\ No newline at end of file
+pub mod resource;
+pub mod time;
+pub mod inspector;
\ No newline at end of file
@@ -3,6 +3,7 @@ use crate::engine::render::renderer::Renderer;
 pub mod renderer;
 pub mod triangle;
 pub mod rect;
+pub mod text;
 
 pub type Point = (usize, usize);
 pub type Color = u32;
@@ -41,12 +41,11 @@ impl Renderer {
     }
 
     pub fn put_pixel(&mut self, x: usize, y: usize, color: Color) {
-        let idx = y * self.width + x;
-        if idx > self.buffer.len() {
+        if x >= self.width || y >= self.height {
             return;
         }
 
-        self.buffer[idx] = color;
+        self.buffer[y * self.width + x] = color;
     }
 
     pub fn draw_line(&mut self, start: Point, end: Point, color: Color) {
@@ -91,6 +90,14 @@ impl Renderer {
         }
     }
 
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     pub fn buf_as_slice(&self) -> &[u32] {
         self.buffer.as_slice()
     }
@@ -0,0 +1,128 @@
+use super::{Color, Position, Renderable, Renderer, Size};
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+// 3x5 bitmap glyphs, rows top to bottom, three bits per row. Lowercase is
+// drawn as uppercase, unknown characters as '?'.
+fn glyph(c: char) -> u16 {
+    match c.to_ascii_uppercase() {
+        'A' => 0b010_101_111_101_101,
+        'B' => 0b110_101_110_101_110,
+        'C' => 0b011_100_100_100_011,
+        'D' => 0b110_101_101_101_110,
+        'E' => 0b111_100_110_100_111,
+        'F' => 0b111_100_110_100_100,
+        'G' => 0b011_100_101_101_011,
+        'H' => 0b101_101_111_101_101,
+        'I' => 0b111_010_010_010_111,
+        'J' => 0b001_001_001_101_010,
+        'K' => 0b101_101_110_101_101,
+        'L' => 0b100_100_100_100_111,
+        'M' => 0b101_111_111_101_101,
+        'N' => 0b110_101_101_101_101,
+        'O' => 0b010_101_101_101_010,
+        'P' => 0b110_101_110_100_100,
+        'Q' => 0b010_101_101_110_011,
+        'R' => 0b110_101_110_101_101,
+        'S' => 0b011_100_010_001_110,
+        'T' => 0b111_010_010_010_010,
+        'U' => 0b101_101_101_101_111,
+        'V' => 0b101_101_101_101_010,
+        'W' => 0b101_101_111_111_101,
+        'X' => 0b101_101_010_101_101,
+        'Y' => 0b101_101_010_010_010,
+        'Z' => 0b111_001_010_100_111,
+        '0' => 0b111_101_101_101_111,
+        '1' => 0b010_110_010_010_111,
+        '2' => 0b110_001_010_100_111,
+        '3' => 0b110_001_010_001_110,
+        '4' => 0b101_101_111_001_001,
+        '5' => 0b111_100_110_001_110,
+        '6' => 0b011_100_111_101_111,
+        '7' => 0b111_001_010_010_010,
+        '8' => 0b111_101_111_101_111,
+        '9' => 0b111_101_111_001_110,
+        ' ' => 0,
+        '.' => 0b000_000_000_000_010,
+        ',' => 0b000_000_000_010_100,
+        ':' => 0b000_010_000_010_000,
+        '=' => 0b000_111_000_111_000,
+        '-' => 0b000_000_111_000_000,
+        '_' => 0b000_000_000_000_111,
+        '+' => 0b000_010_111_010_000,
+        '(' => 0b001_010_010_010_001,
+        ')' => 0b100_010_010_010_100,
+        '[' => 0b110_100_100_100_110,
+        ']' => 0b011_001_001_001_011,
+        '<' => 0b001_010_100_010_001,
+        '>' => 0b100_010_001_010_100,
+        '/' => 0b001_001_010_100_100,
+        '#' => 0b101_111_101_111_101,
+        '!' => 0b010_010_010_000_010,
+        _ => 0b110_001_010_000_010,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Text {
+    pos: Position,
+    content: String,
+    color: Color,
+    scale: usize,
+}
+
+impl Text {
+    pub fn new(position: Position, content: &str, color: Color) -> Self {
+        Self {
+            pos: position,
+            content: content.to_string(),
+            color,
+            scale: 1,
+        }
+    }
+
+    pub fn with_scale(&mut self, scale: usize) -> &mut Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    // Size of a single line of `len` characters, including one column of
+    // spacing after each glyph.
+    pub fn measure(len: usize, scale: usize) -> Size {
+        (
+            len * (GLYPH_WIDTH + 1) * scale,
+            GLYPH_HEIGHT * scale,
+        )
+    }
+
+    pub fn line_height(scale: usize) -> usize {
+        (GLYPH_HEIGHT + 2) * scale
+    }
+}
+
+impl Renderable for Text {
+    fn render(&self, renderer: &mut Renderer) {
+        for (idx, c) in self.content.chars().enumerate() {
+            let bits = glyph(c);
+            let origin_x = self.pos.0 + idx * (GLYPH_WIDTH + 1) * self.scale;
+            for row in 0..GLYPH_HEIGHT {
+                for col in 0..GLYPH_WIDTH {
+                    let shift = (GLYPH_HEIGHT - 1 - row) * GLYPH_WIDTH + (GLYPH_WIDTH - 1 - col);
+                    if bits >> shift & 1 == 0 {
+                        continue;
+                    }
+                    for dy in 0..self.scale {
+                        for dx in 0..self.scale {
+                            renderer.put_pixel(
+                                origin_x + col * self.scale + dx,
+                                self.pos.1 + row * self.scale + dy,
+                                self.color,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
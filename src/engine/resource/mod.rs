@@ -1,15 +1,17 @@
+use crate::engine::ecs::component::Component;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
-struct Sprite {
-    filename: String,
-    coords: Vec<SpriteCoordinates>
+pub struct Sprite {
+    pub filename: String,
+    pub coords: Vec<SpriteCoordinates>
 }
+impl Component for Sprite {}
 
 #[derive(Serialize, Deserialize)]
-struct SpriteCoordinates {
-    start_x: usize,
-    start_y: usize,
-    end_x: usize,
-    end_y: usize
-}
\ No newline at end of file
+pub struct SpriteCoordinates {
+    pub start_x: usize,
+    pub start_y: usize,
+    pub end_x: usize,
+    pub end_y: usize
+}
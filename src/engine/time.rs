@@ -0,0 +1,26 @@
+#[derive(Default, Clone, Debug)]
+pub struct Time {
+    delta: f32,
+    elapsed: f32,
+    frame: u64,
+}
+
+impl Time {
+    pub fn advance(&mut self, delta_time: f32) {
+        self.delta = delta_time;
+        self.elapsed += delta_time;
+        self.frame += 1;
+    }
+
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+}
@@ -1,8 +1,13 @@
-use super::BLOCK_SIZE;
+use super::{BLOCK_LAYER, BLOCK_SIZE, BOARD_LAYER};
 use super::tetromino::{MoveDirection, RotateDirection, Tetromino};
 use crate::engine::ecs::component::{
-    Children, Collider, CollisionSide, Parent, Position as PositionComponent, PositionType, Size,
+    Children, Collider, CollisionSide, Component, Name, Parent, Position as PositionComponent,
+    PositionType, Size,
 };
+use crate::engine::ecs::command::Commands;
+use crate::engine::ecs::event::EventReader;
+use crate::engine::ecs::schedule::{Schedule, Stage};
+use crate::engine::ecs::storage::Tick;
 use crate::engine::ecs::world::{ComponentStorage, Entity, EntityId, World};
 use crate::engine::game::Game;
 use crate::engine::input::InputState;
@@ -15,50 +20,53 @@ use winit::keyboard::KeyCode;
 const BOARD_CELLS: usize = 10;
 const BOARD_ROWS: usize = 20;
 
+pub struct PieceLocked {
+    pub tetromino: EntityId,
+}
+
+// Tag for tetrominos that have stopped falling.
+pub struct Landed;
+impl Component for Landed {}
+
+pub struct LinesCleared {
+    pub count: usize,
+}
+
 pub struct Board {
     entity_id: EntityId,
     shape: Rectangle,
-    cells: [EntityId; BOARD_CELLS * BOARD_ROWS],
+    cells: [Option<EntityId>; BOARD_CELLS * BOARD_ROWS],
 }
 
 impl Board {
     pub fn new(world: &mut World) -> Self {
-        let entity_id = world.spawn();
         let shape_pos = (100, 20);
         let shape_size = (BLOCK_SIZE * BOARD_CELLS, BLOCK_SIZE * BOARD_ROWS);
         let shape = Rectangle::new(shape_pos, shape_size, Renderer::rgb_to_u32(176, 196, 222))
             .with_outline(Renderer::rgb_to_u32(139, 166, 163))
             .to_owned();
 
-        world
-            .add_component(
-                entity_id,
-                PositionComponent {
-                    x: shape_pos.0 as f32,
-                    y: shape_pos.1 as f32,
-                    position_type: PositionType::Abs,
-                },
-            )
-            .add_component(
-                entity_id,
-                Size {
-                    x: shape_size.0,
-                    y: shape_size.1,
-                },
-            )
-            .add_component(entity_id, Children::default())
-            .add_component(
-                entity_id,
-                Collider {
-                    offset: (0, -1),
-                    size: (shape_size.0, shape_size.1 + 1),
-                },
-            );
+        let entity_id = world.spawn_with((
+            PositionComponent {
+                x: shape_pos.0 as f32,
+                y: shape_pos.1 as f32,
+                position_type: PositionType::Abs,
+            },
+            Size {
+                x: shape_size.0,
+                y: shape_size.1,
+            },
+            Children::default(),
+            Name::new("board"),
+            Collider::aabb((0, -1), (shape_size.0, shape_size.1 + 1))
+                .with_layers(BOARD_LAYER, BLOCK_LAYER)
+                .to_owned(),
+        ));
 
         Self {
             entity_id,
             shape,
-            cells: [0; BOARD_CELLS * BOARD_ROWS],
+            cells: [None; BOARD_CELLS * BOARD_ROWS],
         }
     }
 
@@ -76,11 +84,31 @@ pub struct TetrisGame {
     board: Option<Board>,
     current_tetromino: Option<Tetromino>,
     landed_tetrominos: HashMap<EntityId, Tetromino>,
+    cells_tick: Tick,
     timer: f32,
     paused: bool
 }
 
 impl Game for TetrisGame {
+    fn setup(&mut self, schedule: &mut Schedule, world: &mut World) {
+        world.add_event::<PieceLocked>();
+        world.add_event::<LinesCleared>();
+
+        let mut pieces_locked = EventReader::<PieceLocked>::new();
+        schedule.add_system(Stage::PostUpdate, "log_locked_pieces", move |world| {
+            for event in pieces_locked.read(world) {
+                info!("Locked tetromino {}", world.label(event.tetromino));
+            }
+        });
+
+        let mut lines_cleared = EventReader::<LinesCleared>::new();
+        schedule.add_system(Stage::PostUpdate, "log_cleared_lines", move |world| {
+            for event in lines_cleared.read(world) {
+                info!("Cleared {} line(s)", event.count);
+            }
+        });
+    }
+
     fn update(&mut self, delta_time: f32, input_state: &InputState, world: &mut World) {
         self.timer += delta_time;
 
@@ -92,8 +120,6 @@ impl Game for TetrisGame {
             self.paused = !self.paused;
         }
 
-        self.debug(world, input_state, &delta_time);
-
         if self.paused {
             return
         }
@@ -161,6 +187,7 @@ impl TetrisGame {
             board: None,
             current_tetromino: None,
             landed_tetrominos: HashMap::new(),
+            cells_tick: 0,
             timer: 0.0,
             paused: false
         }
@@ -177,15 +204,11 @@ impl TetrisGame {
             for block_id in &block_ids {
                 let collision_info = world.get_collision(*block_id).unwrap();
 
-                for (_, collision) in &collision_info.collision {
-                    if block_ids.contains(&collision.entity_id) {
-                        continue;
-                    }
+                for collision in collision_info.solid() {
                     if collision
-                        .sides
+                        .sides()
                         .iter()
-                        .find(|side| matches!(side, CollisionSide::Bottom(_)))
-                        .is_some()
+                        .any(|side| matches!(side, CollisionSide::Bottom(_)))
                     {
                         can_fall = false;
                     }
@@ -208,11 +231,8 @@ impl TetrisGame {
         for block_id in &block_ids {
             let collision_info = world.get_collision(*block_id).unwrap();
 
-            for (_, collision) in &collision_info.collision {
-                if block_ids.contains(&collision.entity_id) {
-                    continue;
-                }
-                for collision_side in &collision.sides {
+            for collision in collision_info.solid() {
+                for collision_side in collision.sides() {
                     match collision_side {
                         CollisionSide::Left(depth) => {
                             if matches!(move_direction, MoveDirection::Left) {
@@ -228,14 +248,15 @@ impl TetrisGame {
                         }
                         CollisionSide::Bottom(depth) => {
                             // info!("Move down. Bottom collision: {depth}");
+                            world.add_component(tetromino.get_id(), Landed);
+                            world.send_event(PieceLocked {
+                                tetromino: tetromino.get_id(),
+                            });
                             self.landed_tetrominos
                                 .insert(tetromino.get_id(), tetromino.clone());
                             self.current_tetromino = None;
                             return;
                         }
-                        CollisionSide::Contained { x, y } => {
-                            // info!("Contained: {x}, {y}");
-                        }
                         _ => {}
                     }
                 }
@@ -246,11 +267,16 @@ impl TetrisGame {
     }
 
     fn refresh_cells(&mut self, world: &mut World) {
+        if !world.changed_since::<PositionComponent>(self.cells_tick) {
+            return;
+        }
+        self.cells_tick = world.increment_change_tick();
+
         let board = self.board.as_mut().unwrap();
-        board.cells.fill(0);
+        board.cells.fill(None);
         
         for (_, tetromino) in &self.landed_tetrominos {
-            let tetromino_pos = world.positions.get(&tetromino.get_id()).unwrap();
+            let tetromino_pos = world.fetch::<PositionComponent>(&tetromino.get_id()).unwrap();
             let (tetromino_x, tetromino_y) = (
                 (tetromino_pos.x as usize / BLOCK_SIZE),
                 (tetromino_pos.y as usize / BLOCK_SIZE),
@@ -286,7 +312,7 @@ impl TetrisGame {
                     block_pos.1
                 };
                 let block_cell = tetromino_cell + block_row + block_pos.0;
-                board.cells[block_cell] = block.get_id();
+                board.cells[block_cell] = Some(block.get_id());
             }
         }
     }
@@ -294,40 +320,31 @@ impl TetrisGame {
     fn handle_lines(&mut self, world: &mut World) {
         self.refresh_cells(world);
 
+        let mut commands = Commands::new();
+        let mut cleared = 0;
         let board = self.board.as_mut().unwrap();
         for line in board.cells.chunks_exact_mut(BOARD_CELLS) {
-            if line.contains(&0) {
+            if line.contains(&None) {
                 continue;
             }
-
-            for line_block in line.into_iter() {
-                let parent = world.fetch::<Parent>(line_block).unwrap();
-                let tetromino = self.landed_tetrominos.get_mut(&parent.entity).unwrap();
-                tetromino.remove_block(*line_block, world).unwrap();
+            cleared += 1;
+
+            for line_block in line.iter().flatten() {
+                let parent_id = world.fetch::<Parent>(line_block).unwrap().entity;
+                let tetromino = self.landed_tetrominos.get_mut(&parent_id).unwrap();
+                tetromino.remove_block(*line_block, &mut commands).unwrap();
+                if tetromino.blocks.is_empty() {
+                    self.landed_tetrominos.remove(&parent_id);
+                    commands.despawn_recursive(parent_id);
+                }
             }
 
-            line.fill(0);
-        }
-    }
-
-    fn debug(&mut self, world: &mut World, input_state: &InputState, delta_time: &f32) {
-        if !self.paused {
-            return
+            line.fill(None);
         }
+        commands.apply(world);
 
-        let cursor_x: f32 = input_state.mouse_pos.0 as f32;
-        let cursor_y: f32 = input_state.mouse_pos.1 as f32;
-
-        let mut nearest: Vec<(EntityId, f32)> = world.positions.iter()
-            .map(|(&id, pos)| {
-                let dx = pos.x - cursor_x;
-                let dy = pos.y - cursor_y;
-                (id, dx * dx + dy * dy)
-            }).collect();
-        nearest
-            .sort_unstable_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-        for entity_id in &nearest[0..1] {
-            
+        if cleared > 0 {
+            world.send_event(LinesCleared { count: cleared });
         }
     }
 }
@@ -11,4 +11,6 @@ pub(crate) const COLORS: [u32; 4] = [
     4282666183 //blue
 ];
 pub(crate) type Shape = Rectangle;
-pub(crate) const BLOCK_SIZE: usize = (WIN_WIDTH / 28) as usize;
\ No newline at end of file
+pub(crate) const BLOCK_SIZE: usize = (WIN_WIDTH / 28) as usize;
+pub(crate) const BLOCK_LAYER: u32 = 1 << 0;
+pub(crate) const BOARD_LAYER: u32 = 1 << 1;
\ No newline at end of file
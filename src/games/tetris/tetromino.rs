@@ -1,11 +1,15 @@
 #![allow(unused)]
 
 use std::collections::HashMap;
-use super::BLOCK_SIZE;
+use super::{BLOCK_LAYER, BLOCK_SIZE, BOARD_LAYER};
 use super::{COLORS, Shape};
+use crate::engine::ecs::bundle::Bundle;
+use crate::engine::ecs::command::Commands;
+use crate::engine::ecs::prefab::{Prefab, PrefabOverrides};
+use crate::engine::ecs::scene::{Scene, SceneEntity};
 use crate::engine::ecs::component::{
-    Children, Collider, Position as PositionComponent, PositionType, Rotation as RotationComponent,
-    Size,
+    Children, Collider, CollisionIgnore, Component, Position as PositionComponent, PositionType,
+    Rotation as RotationComponent, Size,
 };
 use crate::engine::ecs::world::{ComponentStorage, Entity, EntityId, World};
 use crate::engine::render::rect::Rectangle;
@@ -43,6 +47,44 @@ impl Entity for Block {
     }
 }
 
+pub struct BlockBundle {
+    pub position: PositionComponent,
+    pub size: Size,
+    pub collider: Collider,
+}
+
+impl BlockBundle {
+    pub fn new(shape: &Shape) -> Self {
+        let shape_size = shape.get_size();
+        let shape_pos = shape.get_position();
+
+        Self {
+            position: PositionComponent {
+                x: (shape_pos.0 * BLOCK_SIZE) as f32,
+                y: (shape_pos.1 * BLOCK_SIZE) as f32,
+                position_type: PositionType::Rel,
+            },
+            size: Size {
+                x: shape_size.0,
+                y: shape_size.1,
+            },
+            collider: Collider::aabb((0, 0), (BLOCK_SIZE, BLOCK_SIZE))
+                .with_layers(BLOCK_LAYER, BLOCK_LAYER | BOARD_LAYER)
+                .to_owned(),
+        }
+    }
+}
+
+impl Bundle for BlockBundle {
+    fn insert_into(self, world: &mut World, entity_id: EntityId) {
+        (self.position, self.size, self.collider).insert_into(world, entity_id);
+    }
+}
+
+// Fill colour of a tetromino's blocks, set on the root as a prefab override.
+pub struct BlockColor(pub Color);
+impl Component for BlockColor {}
+
 pub enum MoveDirection {
     Right,
     Left,
@@ -58,47 +100,19 @@ pub struct Tetromino {
     entity_id: EntityId,
     pub blocks: Vec<Block>,
     kind: TetrominoType,
-    color: u32,
 }
 
 impl Tetromino {
     pub fn spawn(world: &mut World) -> Self {
-        let entity_id = world.spawn();
-        world
-            .add_component(entity_id, Children::default())
-            .add_component(
-                entity_id,
-                PositionComponent {
-                    x: (BLOCK_SIZE * 4) as f32,
-                    y: 0.0,
-                    position_type: PositionType::Rel,
-                },
-            );
-
         let mut rng = rand::rng();
         let mut n: u8 = rng.random_range(0..5);
         // n = 4;
-        let tetromino_type = TetrominoType::get_by_index(n);
         let color_code: usize = rng.random_range(0..3);
 
-        let mut tetromino = Tetromino {
-            entity_id,
-            blocks: Vec::with_capacity(4),
-            kind: tetromino_type,
-            color: COLORS[color_code],
-        };
-        tetromino.refresh_shape(world);
-
-        tetromino
-    }
-
-    fn refresh_shape(&mut self, world: &mut World) {
-        self.blocks = self.get_blocks(world, self.color);
-        for block in &self.blocks {
-            world.set_parent(block.entity_id, self.entity_id);
-        }
-
-        // self.refresh_size(world);
+        Tetromino::build()
+            .with_type(TetrominoType::get_by_index(n))
+            .with_color(COLORS[color_code])
+            .spawn(world)
     }
 
     fn refresh_size(&mut self, world: &mut World) { // врахувати позицію блока. Чи потрібен тетроміно компонент Size взагалі?
@@ -127,39 +141,25 @@ impl Tetromino {
         );
     }
 
-    fn get_blocks(&self, world: &mut World, color: Color) -> Vec<Block> {
-        let mut blocks: Vec<Block> = Vec::with_capacity(4);
-        for block_shape in self.kind.get_shapes(color) {
-            let block_entity = world.spawn();
-            blocks.push(Block::new(block_entity, block_shape.to_owned()));
-            let shape_size = block_shape.get_size();
-            let shape_pos = block_shape.get_position();
-            world
-                .add_component(
-                    block_entity,
-                    Size {
-                        x: shape_size.0,
-                        y: shape_size.1,
-                    },
-                )
-                .add_component(
-                    block_entity,
-                    PositionComponent {
-                        x: (shape_pos.0 * BLOCK_SIZE) as f32,
-                        y: (shape_pos.1 * BLOCK_SIZE) as f32,
-                        position_type: PositionType::Rel,
-                    },
-                )
-                .add_component(
-                    block_entity,
-                    Collider {
-                        offset: (0, -1),
-                        size: (BLOCK_SIZE, BLOCK_SIZE + 1),
-                    },
+    fn get_blocks(&self, world: &World, block_ids: &[EntityId]) -> Vec<Block> {
+        let color = world.fetch::<BlockColor>(&self.entity_id).unwrap().0;
+        block_ids
+            .iter()
+            .map(|&block_id| {
+                let position = world.fetch::<PositionComponent>(&block_id).unwrap();
+                let size = world.fetch::<Size>(&block_id).unwrap();
+                let shape = Rectangle::new(
+                    (
+                        position.x as usize / BLOCK_SIZE,
+                        position.y as usize / BLOCK_SIZE,
+                    ),
+                    (size.x, size.y),
+                    color,
                 );
-        }
 
-        blocks
+                Block::new(block_id, shape)
+            })
+            .collect()
     }
 
     pub fn render(&self, renderer: &mut Renderer, world: &World) {
@@ -169,7 +169,7 @@ impl Tetromino {
     }
 
     pub fn shift(&mut self, world: &mut World, move_direction: MoveDirection, speed: f32) {
-        let pos = world.get_mut::<PositionComponent>(&self.entity_id).unwrap();
+        let mut pos = world.get_mut::<PositionComponent>(&self.entity_id).unwrap();
 
         match move_direction {
             MoveDirection::Right => pos.x += speed,
@@ -178,14 +178,18 @@ impl Tetromino {
         }
     }
 
-    pub fn remove_block(&mut self, block_id: EntityId, world: &mut World) -> Result<(), String> {
+    pub fn remove_block(
+        &mut self,
+        block_id: EntityId,
+        commands: &mut Commands,
+    ) -> Result<(), String> {
         if let Some(idx) = self
             .blocks
             .iter()
             .position(|block| block.get_id() == block_id)
         {
             self.blocks.remove(idx);
-            world.remove_entity(block_id);
+            commands.despawn(block_id);
             // self.refresh_size(world);
 
             Ok(())
@@ -194,34 +198,31 @@ impl Tetromino {
         }
     }
 
+    // Turns the blocks a quarter around the root on grid cells, then shifts
+    // them back below the root.
     pub fn rotate(&self, world: &mut World, rotate_direction: RotateDirection) {
-        let mut pos: HashMap<EntityId, (i8, i8)> = HashMap::new();
-        let mut min_y: i8 = 0;
+        let block_size = BLOCK_SIZE as f32;
+        let mut cells: HashMap<EntityId, (i32, i32)> = HashMap::new();
+        let mut min_y: i32 = 0;
         for block in &self.blocks {
             let block_pos = world.fetch::<PositionComponent>(&block.get_id()).unwrap();
-            let new_y = block_pos.x as i8 * -1;
-            pos.insert(block.get_id(), (block_pos.y as i8, new_y));
-            if new_y < min_y {
-                min_y = new_y;
-            }
-        }
-        if min_y < 0 {
-            min_y *= -1;
+            let x = (block_pos.x / block_size).round() as i32;
+            let y = (block_pos.y / block_size).round() as i32;
+            cells.insert(block.get_id(), (y, -x));
+            min_y = min_y.min(-x);
         }
 
-        for (entity_id, block_pos) in pos.iter_mut() {
-            block_pos.1 = block_pos.1 + min_y;
-            let pos = world.get_mut::<PositionComponent>(entity_id).unwrap();
-            pos.x = block_pos.0 as f32;
-            pos.y = block_pos.1 as f32;
+        for (entity_id, (x, y)) in cells {
+            let mut pos = world.get_mut::<PositionComponent>(&entity_id).unwrap();
+            pos.x = x as f32 * block_size;
+            pos.y = (y - min_y) as f32 * block_size;
         }
     }
 
     /** Builder **/
-    pub fn build(world: &mut World) {} //builder
-    pub fn with_type(&mut self, kind: TetrominoType) {}
-    pub fn with_position(&mut self, position: Position) {}
-    pub fn with_rotation(&mut self, rotation: Rotation) {}
+    pub fn build() -> TetrominoBuilder {
+        TetrominoBuilder::default()
+    }
 }
 impl Entity for Tetromino {
     fn get_id(&self) -> EntityId {
@@ -229,6 +230,83 @@ impl Entity for Tetromino {
     }
 }
 
+pub struct TetrominoBuilder {
+    kind: TetrominoType,
+    color: Color,
+    position: Position,
+    rotation: Rotation,
+}
+
+impl Default for TetrominoBuilder {
+    fn default() -> Self {
+        Self {
+            kind: TetrominoType::I,
+            color: COLORS[0],
+            position: (BLOCK_SIZE * 4, 0),
+            rotation: 0,
+        }
+    }
+}
+
+impl TetrominoBuilder {
+    pub fn with_type(&mut self, kind: TetrominoType) -> &mut Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_color(&mut self, color: Color) -> &mut Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_position(&mut self, position: Position) -> &mut Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_rotation(&mut self, rotation: Rotation) -> &mut Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn spawn(&self, world: &mut World) -> Tetromino {
+        let prefab_name = self.kind.prefab_name();
+        if !world.has_prefab(prefab_name) {
+            world.register_prefab(prefab_name, self.kind.prefab());
+        }
+        let instance = world
+            .instantiate(
+                prefab_name,
+                PrefabOverrides::new()
+                    .with(PositionComponent {
+                        x: self.position.0 as f32,
+                        y: self.position.1 as f32,
+                        position_type: PositionType::Rel,
+                    })
+                    .with(BlockColor(self.color)),
+            )
+            .unwrap();
+
+        let mut tetromino = Tetromino {
+            entity_id: instance.root,
+            blocks: Vec::with_capacity(4),
+            kind: self.kind.clone(),
+        };
+        let block_ids = &instance.scene.entities[1..];
+        // Blocks of one tetromino always touch, keep them out of each other's
+        // collisions.
+        for block_id in block_ids {
+            world.add_component(*block_id, CollisionIgnore::new(block_ids.iter().copied()));
+        }
+        tetromino.blocks = tetromino.get_blocks(world, block_ids);
+        for _ in 0..self.rotation % 4 {
+            tetromino.rotate(world, RotateDirection::Left);
+        }
+
+        tetromino
+    }
+}
+
 #[derive(Clone)]
 pub enum TetrominoType {
     I,
@@ -249,6 +327,44 @@ impl TetrominoType {
         }
     }
 
+    pub fn prefab_name(&self) -> &'static str {
+        match self {
+            TetrominoType::I => "tetromino_i",
+            TetrominoType::O => "tetromino_o",
+            TetrominoType::T => "tetromino_t",
+            TetrominoType::L => "tetromino_l",
+            TetrominoType::S => "tetromino_s",
+        }
+    }
+
+    // Root entity followed by one child per block, the root position is
+    // overridden per instance.
+    pub fn prefab(&self) -> Prefab {
+        let root = SceneEntity::named("tetromino")
+            .with_component(
+                "Position",
+                &PositionComponent {
+                    x: 0.0,
+                    y: 0.0,
+                    position_type: PositionType::Rel,
+                },
+            )
+            .unwrap();
+        let mut entities = vec![root];
+        for (idx, shape) in self.get_shapes(COLORS[0]).iter().enumerate() {
+            let block = BlockBundle::new(shape);
+            let entity = SceneEntity::named(&format!("block_{idx}"))
+                .with_parent("tetromino")
+                .with_component("Position", &block.position)
+                .and_then(|entity| entity.with_component("Size", &block.size))
+                .and_then(|entity| entity.with_component("Collider", &block.collider))
+                .unwrap();
+            entities.push(entity);
+        }
+
+        Prefab::new(Scene { entities }).unwrap()
+    }
+
     pub fn get_shapes(&self, color: Color) -> Vec<Shape> {
         match self {
             TetrominoType::I => {
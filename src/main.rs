@@ -1,8 +1,8 @@
 mod games;
-mod engine;
 mod app;
 
 use env_logger::Builder;
+use gameengine::engine;
 use winit::event_loop::{ControlFlow, EventLoop};
 use crate::app::App;
 use crate::engine::ecs::world::World;